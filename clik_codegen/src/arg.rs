@@ -2,8 +2,8 @@ use crate::quote;
 use std::collections::HashMap;
 
 use syn::{
-    punctuated::Punctuated, spanned::Spanned, token::Comma, Attribute, FnArg, Pat, PatType, Type,
-    TypePath,
+    punctuated::Punctuated, spanned::Spanned, token::Comma, Attribute, Expr, FnArg,
+    GenericArgument, Pat, PatType, PathArguments, ReturnType, Type, TypePath,
 };
 
 use crate::attr;
@@ -12,17 +12,59 @@ use crate::attr;
 pub struct Arg<'a> {
     pub pos: usize,
     pub arg: &'a PatType,
+    /// The type the supplied value gets parsed into
+    pub ty: &'a Type,
+    pub kind: ArgKind,
+    /// Whether the argument has to be supplied
+    pub required: bool,
     pub help: Option<String>,
+    /// The names of the argument if it is a named option
+    pub opt: Option<Opt>,
+    /// The value to use if the argument is not supplied
+    pub default: Option<Expr>,
 }
 
-/// Parses out the arguments to the command from the function signature
+impl Arg<'_> {
+    /// The name of the argument
+    pub fn name(&self) -> String {
+        let pat = &self.arg.pat;
+        quote!(#pat).to_string()
+    }
+
+    /// Whether the argument is a `bool` option that is set by its presence
+    pub fn is_flag(&self) -> bool {
+        self.kind == ArgKind::Single && matches!(self.ty, Type::Path(p) if p.path.is_ident("bool"))
+    }
+}
+
+/// The names of a named option, at least one of them is set
+#[derive(Debug)]
+pub struct Opt {
+    pub short: Option<char>,
+    pub long: Option<String>,
+}
+
+/// Describes how an argument is supplied to the command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    /// The argument consists of exactly one value
+    Single,
+    /// The argument is an `Option<T>` and may be omitted
+    Optional,
+    /// The argument is a `Vec<T>` that collects all remaining values
+    Variadic,
+}
+
+/// Parses out the arguments to the command from the function signature,
+/// separated into the positional arguments and the named options
 /// # Arguments
 /// * `args` - The arguments supplied to the function
 /// * `attr` - The attributes for the function to parse `clik_arg`, etc...
+#[allow(clippy::type_complexity)]
 pub fn parse_args<'a>(
     args: &'a Punctuated<FnArg, Comma>,
     attr: &Vec<Attribute>,
-) -> Result<Vec<Arg<'a>>, proc_macro2::TokenStream> {
+) -> Result<(Vec<Arg<'a>>, Vec<Arg<'a>>), proc_macro2::TokenStream> {
     let mut res: HashMap<String, Arg<'a>> = HashMap::new();
 
     // Parse args out
@@ -31,6 +73,11 @@ pub fn parse_args<'a>(
             continue;
         }
         if let syn::FnArg::Typed(arg) = arg {
+            // The piped input is not supplied on the command line
+            if is_pipe(&arg.ty) {
+                continue;
+            }
+
             let name = match arg.pat.as_ref() {
                 Pat::Ident(i) => i.ident.to_string(),
                 _ => {
@@ -39,12 +86,24 @@ pub fn parse_args<'a>(
                     )
                 }
             };
+            let (ty, kind) = if let Some(inner) = generic_inner(&arg.ty, "Option") {
+                (inner, ArgKind::Optional)
+            } else if let Some(inner) = generic_inner(&arg.ty, "Vec") {
+                (inner, ArgKind::Variadic)
+            } else {
+                (arg.ty.as_ref(), ArgKind::Single)
+            };
             res.insert(
                 name,
                 Arg {
                     pos: res.len(),
-                    arg: arg,
+                    arg,
+                    ty,
+                    kind,
+                    required: kind == ArgKind::Single,
                     help: None,
+                    opt: None,
+                    default: None,
                 },
             );
         }
@@ -53,14 +112,191 @@ pub fn parse_args<'a>(
     // Now parse attrs for descriptions
     for attr in attr {
         if attr.path().is_ident("clik_arg") {
-            attr::parse_attr(&attr, &mut res)?
+            attr::parse_attr(attr, &mut res)?
+        } else if attr.path().is_ident("clik_opt") {
+            attr::parse_opt_attr(attr, &mut res)?
         }
     }
 
-    // Convert the hashmap to a vector and sort it
-    let mut vec: Vec<Arg<'a>> = res.into_iter().map(|f| f.1).collect();
-    vec.sort_by(|a, b| a.pos.cmp(&b.pos));
-    Ok(vec)
+    // Convert the hashmap to vectors and sort them
+    let (mut vec, mut opts): (Vec<Arg<'a>>, Vec<Arg<'a>>) =
+        res.into_values().partition(|a| a.opt.is_none());
+    vec.sort_by_key(|a| a.pos);
+    opts.sort_by_key(|a| a.pos);
+
+    // Positions are counted separately for arguments and options
+    for (i, arg) in vec.iter_mut().enumerate() {
+        arg.pos = i;
+    }
+    for (i, opt) in opts.iter_mut().enumerate() {
+        opt.pos = i;
+    }
+
+    check_opts(&opts)?;
+
+    // Optional arguments can only be omitted if nothing follows them
+    // and variadic arguments consume everything that follows them
+    let mut optional = false;
+    let mut variadic = false;
+    for arg in &vec {
+        let error = if variadic {
+            "Variadic arguments must be the last argument"
+        } else if optional && arg.required {
+            "Required arguments must not follow optional arguments"
+        } else {
+            optional |= !arg.required;
+            variadic |= arg.kind == ArgKind::Variadic;
+            continue;
+        };
+        return Err(syn::Error::new(arg.arg.span(), error).into_compile_error());
+    }
+
+    Ok((vec, opts))
+}
+
+/// Ensures that options are either flags or optional and that their names are unique
+/// # Arguments
+/// * `opts` - The options to check
+fn check_opts(opts: &[Arg]) -> Result<(), proc_macro2::TokenStream> {
+    for (i, arg) in opts.iter().enumerate() {
+        let error = if !arg.is_flag() && arg.kind != ArgKind::Optional {
+            "Options must be of the type `bool` or `Option<T>`"
+        } else if opts[..i].iter().any(|other| {
+            let (a, b) = (arg.opt.as_ref().unwrap(), other.opt.as_ref().unwrap());
+            (a.short.is_some() && a.short == b.short) || (a.long.is_some() && a.long == b.long)
+        }) {
+            "The name of this option is already in use"
+        } else {
+            continue;
+        };
+        return Err(syn::Error::new(arg.arg.span(), error).into_compile_error());
+    }
+
+    Ok(())
+}
+
+/// Returns the generic argument of a type of the form `<wrapper><T>`, e.g. `Option<T>`
+/// # Arguments
+/// * `ty` - The type to inspect
+/// * `wrapper` - The name of the wrapping type
+fn generic_inner<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    path_generic_inner(path, wrapper)
+}
+
+/// Returns the generic argument of a type path of the form `<wrapper><T>`, e.g. `Context<T>`
+/// # Arguments
+/// * `path` - The type path to inspect
+/// * `wrapper` - The name of the wrapping type
+fn path_generic_inner<'a>(path: &'a TypePath, wrapper: &str) -> Option<&'a Type> {
+    match generic_types(path, wrapper)?.as_slice() {
+        [inner] => Some(inner),
+        _ => None,
+    }
+}
+
+/// Returns the generic type arguments of a type path of the form `<wrapper><...>`,
+/// lifetimes are left out
+/// # Arguments
+/// * `path` - The type path to inspect
+/// * `wrapper` - The name of the wrapping type
+fn generic_types<'a>(path: &'a TypePath, wrapper: &str) -> Option<Vec<&'a Type>> {
+    let segment = path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    let PathArguments::AngleBracketed(generics) = &segment.arguments else {
+        return None;
+    };
+    let types = generics.args.iter().filter_map(|arg| match arg {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    });
+    Some(types.collect())
+}
+
+/// Whether a type is `Pipe<T>`, the type of the argument receiving the piped input
+/// # Arguments
+/// * `ty` - The type to inspect
+fn is_pipe(ty: &Type) -> bool {
+    generic_inner(ty, "Pipe").is_some()
+}
+
+/// Retrieves the argument receiving the piped input of the command, if there is one
+///
+/// If there are any errors, they get output in the form of a compile error packed into a `TokenStream`
+pub fn get_pipe_var(
+    args: &Punctuated<FnArg, Comma>,
+) -> Result<Option<&PatType>, proc_macro2::TokenStream> {
+    let mut pipes = args.iter().skip(1).filter_map(|arg| match arg {
+        FnArg::Typed(arg) if is_pipe(&arg.ty) => Some(arg),
+        _ => None,
+    });
+
+    let pipe = pipes.next();
+    if let Some(other) = pipes.next() {
+        return Err(syn::Error::new(
+            other.span(),
+            "Only one argument can receive the piped input",
+        )
+        .into_compile_error());
+    }
+    Ok(pipe)
+}
+
+/// Retrieves the type of the value a command passes to the next command of a pipeline.
+/// This is the success type of the declared return type, if it is not `()`
+/// # Arguments
+/// * `output` - The declared return type of the function
+pub fn get_pipe_output(output: &ReturnType) -> Option<&Type> {
+    match get_result_types(output)?.first() {
+        Some(Type::Tuple(unit)) if unit.elems.is_empty() => None,
+        value => value.copied(),
+    }
+}
+
+/// Retrieves the error type of a command, this is the error type of the declared return type.
+/// Aliases like `io::Result<T>` hide the error type, so the return type has to be `Result<T, E>`
+///
+/// If there are any errors, they get output in the form of a compile error packed into a `TokenStream`
+/// # Arguments
+/// * `output` - The declared return type of the function
+pub fn get_error_type(output: &ReturnType) -> Result<Option<&Type>, proc_macro2::TokenStream> {
+    let ReturnType::Type(_, ty) = output else {
+        return Ok(None);
+    };
+    match get_result_types(output).as_deref() {
+        Some([_, error]) => Ok(Some(error)),
+        _ => Err(syn::Error::new_spanned(
+            ty,
+            "Commands have to return `Result<T, E>` with the error type spelled out",
+        )
+        .into_compile_error()),
+    }
+}
+
+/// Retrieves the generic types of a declared return type of the form `Result<...>`
+/// # Arguments
+/// * `output` - The declared return type of the function
+fn get_result_types(output: &ReturnType) -> Option<Vec<&Type>> {
+    let ReturnType::Type(_, ty) = output else {
+        return None;
+    };
+    let Type::Path(path) = ty.as_ref() else {
+        return None;
+    };
+    generic_types(path, "Result")
+}
+
+/// Retrieves the state type of a state variable of the type `&mut Context<T>` or
+/// `&mut Context<T, E>`, such a command receives the context of its invocation
+/// instead of the bare state
+/// # Arguments
+/// * `state` - The type the state variable references
+pub fn get_context_state(state: &TypePath) -> Option<&Type> {
+    generic_types(state, "Context")?.first().copied()
 }
 
 /// Retrieves the state variable type from the arguments
@@ -69,8 +305,8 @@ pub fn parse_args<'a>(
 /// and that it is a `&mut` reference.
 ///
 /// If there are any errors, they get output in the form of a compile error packed into a `TokenStream`
-pub fn get_state_var<'a>(
-    args: &'a Punctuated<FnArg, Comma>,
+pub fn get_state_var(
+    args: &Punctuated<FnArg, Comma>,
 ) -> Result<TypePath, proc_macro2::TokenStream> {
     Err(match args.first() {
         Some(first) => match first {
@@ -91,39 +327,267 @@ pub fn get_state_var<'a>(
     .into_compile_error())
 }
 
+/// Renders a type as a compact string, e.g. `Vec<String>` instead of `Vec < String >`
+/// # Arguments
+/// * `ty` - The type to render
+pub fn type_name(ty: &Type) -> String {
+    compact_tokens(quote!(#ty))
+}
+
+/// Renders tokens as a compact string, only keeping the necessary spaces
+/// # Arguments
+/// * `tokens` - The tokens to render
+fn compact_tokens(tokens: proc_macro2::TokenStream) -> String {
+    let tokens = tokens.to_string();
+    let mut res = String::new();
+    let mut chars = tokens.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == ' ' {
+            // Only keep spaces that separate two words or follow a comma
+            let prev = res.chars().last().unwrap_or(' ');
+            let next = chars.peek().copied().unwrap_or(' ');
+            let is_word = |c: char| c.is_alphanumeric() || c == '_';
+            if !((is_word(prev) && is_word(next)) || prev == ',') {
+                continue;
+            }
+        }
+        res.push(c);
+    }
+
+    res
+}
+
+/// Takes in an argument vector and creates a vector of TokenStreams
+/// from them. These TokenStreams construct the `clik::ArgSpec` for each argument
+/// # Arguments
+/// * `args` - The arguments to describe
+pub fn create_arg_specs(args: &[Arg]) -> Vec<proc_macro2::TokenStream> {
+    args.iter()
+        .map(|arg| {
+            let ident = &arg.arg.pat;
+            let ty = type_name(arg.ty);
+            let pos = arg.pos;
+            let required = arg.required;
+            let variadic = arg.kind == ArgKind::Variadic;
+            let default = match &arg.default {
+                Some(default) => {
+                    let default = compact_tokens(quote!(#default));
+                    quote!(Some(#default))
+                }
+                None => quote!(None),
+            };
+            let help = match &arg.help {
+                Some(help) => quote!(Some(#help)),
+                None => quote!(None),
+            };
+
+            quote! {
+                clik::ArgSpec {
+                    name: stringify!(#ident),
+                    ty: #ty,
+                    position: #pos,
+                    help: #help,
+                    required: #required,
+                    variadic: #variadic,
+                    default: #default,
+                }
+            }
+        })
+        .collect()
+}
+
+/// Takes in an option vector and creates a vector of TokenStreams
+/// from them. These TokenStreams construct the `clik::OptSpec` for each option
+/// # Arguments
+/// * `opts` - The options to describe
+pub fn create_opt_specs(opts: &[Arg]) -> Vec<proc_macro2::TokenStream> {
+    opts.iter()
+        .map(|arg| {
+            let ident = &arg.arg.pat;
+            let ty = type_name(arg.ty);
+            let opt = arg.opt.as_ref().expect("Expected option to have names");
+            let short = match opt.short {
+                Some(short) => quote!(Some(#short)),
+                None => quote!(None),
+            };
+            let long = match &opt.long {
+                Some(long) => quote!(Some(#long)),
+                None => quote!(None),
+            };
+            let help = match &arg.help {
+                Some(help) => quote!(Some(#help)),
+                None => quote!(None),
+            };
+            let flag = arg.is_flag();
+
+            quote! {
+                clik::OptSpec {
+                    name: stringify!(#ident),
+                    ty: #ty,
+                    short: #short,
+                    long: #long,
+                    help: #help,
+                    flag: #flag,
+                }
+            }
+        })
+        .collect()
+}
+
+/// Takes in an option vector and creates the TokenStream that separates
+/// the options from the positional arguments and parses the options.
+/// Afterwards, `args` only contains the positional arguments and `positions`
+/// their positions within the supplied arguments
+/// # Arguments
+/// * `opts` - The options to parse
+/// * `error_type` - The error type of the command
+/// * `path` - The expression for the path of the command, describing errors
+pub fn create_opt_block(
+    opts: Vec<Arg>,
+    error_type: &proc_macro2::TokenStream,
+    path: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    if opts.is_empty() {
+        return quote!();
+    }
+
+    let specs = create_opt_specs(&opts);
+    let mut blocks: Vec<proc_macro2::TokenStream> = Vec::new();
+
+    for arg in opts {
+        let ty = &arg.arg.ty;
+        let ident = &arg.arg.pat;
+        let i = arg.pos;
+
+        let block = if arg.is_flag() {
+            quote! {
+                let #ident: #ty = options[#i].is_some();
+            }
+        } else {
+            let value = create_parse_value(&arg, quote!(*position), path);
+            quote! {
+                let #ident: #ty = match &options[#i] {
+                    None => None,
+                    Some(clik::OptValue { position, value: v }) => Some(#value)
+                };
+            }
+        };
+
+        blocks.push(block);
+    }
+
+    quote! {
+        #[allow(unused_variables)]
+        let clik::SplitArgs { args, positions, options } = clik::split_options::<#error_type>(args, &[#(#specs),*], #path)?;
+        #(#blocks)*
+    }
+}
+
+/// Creates the TokenStream that rejects more positional arguments than the
+/// command declares, commands with a variadic argument accept all of them
+/// # Arguments
+/// * `args` - The declared arguments
+/// * `path` - The expression for the path of the command, describing errors
+/// * `split` - Whether the options have been split off, see `position`
+pub fn create_arity_block(
+    args: &[Arg],
+    path: &proc_macro2::TokenStream,
+    split: bool,
+) -> proc_macro2::TokenStream {
+    if args.last().is_some_and(|a| a.kind == ArgKind::Variadic) {
+        return quote!();
+    }
+
+    let count = args.len();
+    let position = position(quote!(#count), split);
+    quote! {
+        if args.len() > #count {
+            return Err(
+                clik::error::Error::TooManyArguments {
+                    path: (#path).iter().map(ToString::to_string).collect(),
+                    position: #position,
+                    arguments: args[#count..].to_vec()
+                }.into());
+        }
+    }
+}
+
 /// Takes in an argument vector and creates a vector of TokenStreams
 /// from them. These TokenStreams contain the parsing blocks for each argument
 /// # Arguments
 /// * `args` - The vector of arguments to transform
-pub fn create_parse_blocks<'a>(args: Vec<Arg<'a>>) -> Vec<proc_macro2::TokenStream> {
+/// * `path` - The expression for the path of the command, describing errors
+/// * `split` - Whether the options have been split off, see `position`
+pub fn create_parse_blocks(
+    args: Vec<Arg>,
+    path: &proc_macro2::TokenStream,
+    split: bool,
+) -> Vec<proc_macro2::TokenStream> {
     let mut res: Vec<proc_macro2::TokenStream> = Vec::new();
 
     for arg in args {
         let ty = &arg.arg.ty;
+        let ty_name = type_name(arg.ty);
         let ident = &arg.arg.pat;
-        let i = res.len();
+        let i = arg.pos;
+        let value = create_parse_value(&arg, position(quote!(#i), split), path);
 
-        let block = quote! {
-            let #ident: #ty = {
-                match match args.get(#i) {
+        let block = match arg.kind {
+            ArgKind::Single if arg.default.is_some() => {
+                let default = &arg.default;
+                quote! {
+                    let #ident: #ty = match args.get(#i) {
+                        None => #default,
+                        Some(v) => #value
+                    };
+                }
+            }
+            ArgKind::Single => quote! {
+                let #ident: #ty = match args.get(#i) {
                     None => return Err(
-                        clik::error::MissingArgumentError {
+                        clik::error::Error::MissingArgument {
+                            path: (#path).iter().map(ToString::to_string).collect(),
                             name: stringify!(#ident).to_string(),
                             position: #i,
-                            ty: stringify!(#ty).to_string()
+                            ty: #ty_name.to_string()
                         }.into()),
-                    Some(v) => v
-                }.parse::<#ty>() {
-                    Ok(v) => v,
-                    Err(e) => return Err(
-                        clik::error::WrongArgumentError {
-                            name: stringify!(#ident).to_string(),
-                            position: #i,
-                            ty: stringify!(#ty).to_string(),
-                            inner: e.into()
-                        }.into())
+                    Some(v) => #value
+                };
+            },
+            ArgKind::Optional => quote! {
+                let #ident: #ty = match args.get(#i) {
+                    None => None,
+                    Some(v) => Some(#value)
+                };
+            },
+            ArgKind::Variadic => {
+                let value = create_parse_value(&arg, position(quote!(i), split), path);
+                let check = arg.required.then(|| {
+                    quote! {
+                        if args.len() <= #i {
+                            return Err(
+                                clik::error::Error::MissingArgument {
+                                    path: (#path).iter().map(ToString::to_string).collect(),
+                                    name: stringify!(#ident).to_string(),
+                                    position: #i,
+                                    ty: #ty_name.to_string()
+                                }.into());
+                        }
+                    }
+                });
+                quote! {
+                    let #ident: #ty = {
+                        #check
+
+                        let mut values = Vec::new();
+                        for (i, v) in args.iter().enumerate().skip(#i) {
+                            values.push(#value);
+                        }
+                        values
+                    };
                 }
-            };
+            }
         };
 
         res.push(block);
@@ -131,3 +595,45 @@ pub fn create_parse_blocks<'a>(args: Vec<Arg<'a>>) -> Vec<proc_macro2::TokenStre
 
     res
 }
+
+/// Creates the expression for the position of the positional argument at `index`
+/// within the supplied arguments. Once the options have been split off, the
+/// position is looked up in `positions`, otherwise both are the same
+/// # Arguments
+/// * `index` - The expression for the index among the positional arguments
+/// * `split` - Whether the options have been split off
+fn position(index: proc_macro2::TokenStream, split: bool) -> proc_macro2::TokenStream {
+    match split {
+        true => quote!(positions[#index]),
+        false => index,
+    }
+}
+
+/// Creates the expression that parses the supplied value `v` of an argument
+/// # Arguments
+/// * `arg` - The argument to parse the value for
+/// * `i` - The expression for the position of the value
+/// * `path` - The expression for the path of the command, describing errors
+fn create_parse_value(
+    arg: &Arg,
+    i: proc_macro2::TokenStream,
+    path: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let ty = arg.ty;
+    let ty_name = type_name(ty);
+    let ident = &arg.arg.pat;
+
+    quote! {
+        match v.parse::<#ty>() {
+            Ok(v) => v,
+            Err(e) => return Err(
+                clik::error::Error::InvalidArgument {
+                    path: (#path).iter().map(ToString::to_string).collect(),
+                    name: stringify!(#ident).to_string(),
+                    position: #i,
+                    ty: #ty_name.to_string(),
+                    inner: e.into()
+                }.into())
+        }
+    }
+}
@@ -1,29 +1,30 @@
 use std::collections::HashMap;
 
-use syn::{punctuated::Punctuated, spanned::Spanned, Attribute, Expr, Lit, Token};
+use syn::{punctuated::Punctuated, spanned::Spanned, Attribute, Expr, ExprLit, Lit, Token};
 
-use crate::arg::Arg;
+use crate::arg::{Arg, ArgKind, Opt};
 
 /// Parses an attribute into the `args` Vec
 pub fn parse_attr<'a>(
     attr: &Attribute,
     args: &mut HashMap<String, Arg<'a>>,
 ) -> Result<(), proc_macro2::TokenStream> {
-    let mut nested = attr
+    let nested = attr
         .parse_args_with(Punctuated::<Expr, Token![,]>::parse_terminated)
         .unwrap();
-    if nested.len() != 2 {
+    if nested.len() < 2 {
         return Err(syn::Error::new(
             attr.span(),
-            "Expected exactly 2 arguments: (<argument>, <description>)",
+            "Expected at least 2 arguments: (<argument>, <description>, <options>...)",
         )
         .to_compile_error());
     }
 
-    let second = nested.pop().expect("Expected second arg to exist");
-    let first = nested.pop().expect("Expected first arg to exist");
+    let mut nested = nested.into_iter();
+    let first = nested.next().expect("Expected first arg to exist");
+    let second = nested.next().expect("Expected second arg to exist");
 
-    let second = match second.value() {
+    let second = match &second {
         Expr::Lit(l) => match &l.lit {
             Lit::Str(l) => l.value(),
             _ => {
@@ -39,23 +40,143 @@ pub fn parse_attr<'a>(
         }
     };
 
-    match first.value() {
+    let arg = find_arg(&first, args)?;
+    arg.help = Some(second);
+
+    for option in nested {
+        parse_arg_option(&option, arg)?;
+    }
+
+    Ok(())
+}
+
+/// Parses a `clik_opt` attribute, turning the argument into a named option
+pub fn parse_opt_attr(
+    attr: &Attribute,
+    args: &mut HashMap<String, Arg<'_>>,
+) -> Result<(), proc_macro2::TokenStream> {
+    let nested = attr
+        .parse_args_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+        .unwrap();
+
+    let mut nested = nested.into_iter();
+    let Some(first) = nested.next() else {
+        return Err(syn::Error::new(
+            attr.span(),
+            "Expected at least 1 argument: (<argument>, <names>..., <description>)",
+        )
+        .to_compile_error());
+    };
+
+    let arg = find_arg(&first, args)?;
+    let mut opt = Opt {
+        short: None,
+        long: None,
+    };
+
+    for option in nested {
+        let error = match &option {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(help),
+                ..
+            }) => {
+                arg.help = Some(help.value());
+                continue;
+            }
+            Expr::Assign(assign) => match (assign.left.as_ref(), assign.right.as_ref()) {
+                (
+                    Expr::Path(p),
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Char(short),
+                        ..
+                    }),
+                ) if p.path.is_ident("short") => {
+                    opt.short = Some(short.value());
+                    continue;
+                }
+                (
+                    Expr::Path(p),
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(long),
+                        ..
+                    }),
+                ) if p.path.is_ident("long") => {
+                    opt.long = Some(long.value());
+                    continue;
+                }
+                _ => "Expected `short = '<char>'` or `long = \"<name>\"`",
+            },
+            _ => "Expected `short = '<char>'`, `long = \"<name>\"` or a literal description",
+        };
+        return Err(syn::Error::new(option.span(), error).to_compile_error());
+    }
+
+    // Without any explicit names, the option is named after the argument
+    if opt.short.is_none() && opt.long.is_none() {
+        opt.long = Some(arg.name().replace('_', "-"));
+    }
+
+    arg.opt = Some(opt);
+    Ok(())
+}
+
+/// Looks up the argument the first argument of an attribute refers to
+/// # Arguments
+/// * `first` - The first argument of the attribute
+/// * `args` - The arguments of the function
+fn find_arg<'b, 'a>(
+    first: &Expr,
+    args: &'b mut HashMap<String, Arg<'a>>,
+) -> Result<&'b mut Arg<'a>, proc_macro2::TokenStream> {
+    match first {
         Expr::Path(p) => {
             let ident = p.path.require_ident().unwrap();
             match args.get_mut(&ident.to_string()) {
-                Some(i) => {
-                    i.help = Some(second);
-                }
-                None => {
-                    return Err(
-                        syn::Error::new(first.span(), "Describing non-existing argument")
-                            .to_compile_error(),
-                    );
-                }
+                Some(i) => Ok(i),
+                None => Err(
+                    syn::Error::new(first.span(), "Describing non-existing argument")
+                        .to_compile_error(),
+                ),
+            }
+        }
+        _ => Err(syn::Error::new(first.span(), "Expected ident").to_compile_error()),
+    }
+}
+
+/// Parses an additional option of a `clik_arg` attribute into `arg`
+/// # Arguments
+/// * `option` - The option expression
+/// * `arg` - The argument the option applies to
+fn parse_arg_option(option: &Expr, arg: &mut Arg) -> Result<(), proc_macro2::TokenStream> {
+    match option {
+        Expr::Path(p) if p.path.is_ident("required") => {
+            if arg.kind != ArgKind::Variadic {
+                return Err(syn::Error::new(
+                    option.span(),
+                    "Only variadic arguments can be marked as 'required'",
+                )
+                .to_compile_error());
+            }
+            arg.required = true;
+        }
+        Expr::Assign(assign) if matches!(assign.left.as_ref(), Expr::Path(p) if p.path.is_ident("default")) =>
+        {
+            if arg.kind != ArgKind::Single {
+                return Err(syn::Error::new(
+                    option.span(),
+                    "Only arguments that are neither optional nor variadic can have a default",
+                )
+                .to_compile_error());
             }
+            arg.required = false;
+            arg.default = Some(assign.right.as_ref().clone());
         }
         _ => {
-            return Err(syn::Error::new(first.span(), "Expected ident").to_compile_error());
+            return Err(syn::Error::new(
+                option.span(),
+                "Unknown option, expected 'required' or 'default = <expr>'",
+            )
+            .to_compile_error());
         }
     }
 
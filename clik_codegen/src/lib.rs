@@ -10,13 +10,16 @@ use proc_macro2::{Ident, Span};
 use quote::quote;
 use syn::{
     parse::{Parse, ParseStream},
+    spanned::Spanned,
     token::Comma,
-    LitStr,
+    FnArg, LitStr, ReturnType,
 };
 
 struct ClikCommandArgs {
     name: Ident,
     help: LitStr,
+    /// Whether arguments beyond the declared ones are ignored instead of rejected
+    extra_args: bool,
 }
 
 impl Parse for ClikCommandArgs {
@@ -24,7 +27,21 @@ impl Parse for ClikCommandArgs {
         let name: Ident = input.parse()?;
         input.parse::<Comma>()?;
         let help: LitStr = input.parse()?;
-        Ok(ClikCommandArgs { name, help })
+
+        let mut extra_args = false;
+        while input.parse::<Option<Comma>>()?.is_some() && !input.is_empty() {
+            let option: Ident = input.parse()?;
+            match option.to_string().as_str() {
+                "extra_args" => extra_args = true,
+                _ => return Err(syn::Error::new(option.span(), "Expected `extra_args`")),
+            }
+        }
+
+        Ok(ClikCommandArgs {
+            name,
+            help,
+            extra_args,
+        })
     }
 }
 
@@ -34,10 +51,11 @@ pub fn clik_command(attr: TokenStream, input: TokenStream) -> TokenStream {
     let input_args = syn::parse_macro_input!(attr as ClikCommandArgs);
     let command_name = input_args.name;
     let command_help = input_args.help;
+    let extra_args = input_args.extra_args;
 
     let input = syn::parse_macro_input!(input as syn::ItemFn);
 
-    let args = match arg::parse_args(&input.sig.inputs, &input.attrs) {
+    let (args, opts) = match arg::parse_args(&input.sig.inputs, &input.attrs) {
         Ok(a) => a,
         Err(e) => return e.into(),
     };
@@ -46,29 +64,140 @@ pub fn clik_command(attr: TokenStream, input: TokenStream) -> TokenStream {
         Ok(var) => var,
         Err(err) => return err.into(),
     };
+    let context_state = arg::get_context_state(&state_var);
 
-    for attr in &input.attrs {
-        if attr.path().is_ident("clik_arg") {}
-    }
+    let pipe_var = match arg::get_pipe_var(&input.sig.inputs) {
+        Ok(var) => var,
+        Err(err) => return err.into(),
+    };
+    let pipe_output = arg::get_pipe_output(&input.sig.output);
+    let error_type = match arg::get_error_type(&input.sig.output) {
+        Ok(Some(error)) => quote!(#error),
+        Ok(None) => quote!(Box<dyn std::error::Error>),
+        Err(err) => return err.into(),
+    };
+    let return_type = match &input.sig.output {
+        ReturnType::Type(_, ty) => quote!(#ty),
+        ReturnType::Default => quote!(Result<(), #error_type>),
+    };
 
     let fn_name = &input.sig.ident;
     let new_fn_name = proc_macro2::Ident::new(&format!("function_{}", fn_name), Span::call_site());
-    let state_type = &state_var;
-    let arg_blocks = arg::create_parse_blocks(args);
+    // The name of the context parameter must not clash with the arguments of the function
+    let context = proc_macro2::Ident::new("__clik_context", Span::call_site());
+    let state_type = match context_state {
+        Some(state) => quote!(#state),
+        None => quote!(#state_var),
+    };
+    // The context is passed as `__clik_context` and, once the arguments have been parsed,
+    // bound to the first argument as declared, the bare state is bound to `state`
+    let (context_type, bind_block) = match (context_state, input.sig.inputs.first()) {
+        (Some(_), Some(FnArg::Typed(first))) => {
+            let (pat, ty) = (&first.pat, &first.ty);
+            (quote!(#ty), quote!(let #pat: #ty = #context;))
+        }
+        _ => (
+            quote!(&mut clik::Context<'_, #state_type, #error_type>),
+            quote!(let state: &mut #state_type = #context.state_mut();),
+        ),
+    };
+    let arg_specs = arg::create_arg_specs(&args);
+    let opt_specs = arg::create_opt_specs(&opts);
+    // Errors are described with the path the command has been invoked with
+    let path = quote!(#context.path());
+    // Without arguments and options, words besides the subcommands are unknown subcommands
+    let no_args = match args.is_empty() && opts.is_empty() && !extra_args {
+        true => quote!(.without_args()),
+        false => quote!(),
+    };
+    let split = !opts.is_empty();
+    let opt_block = arg::create_opt_block(opts, &error_type, &path);
+    let arity_block = match extra_args {
+        true => quote!(),
+        false => arg::create_arity_block(&args, &path, split),
+    };
+    let arg_blocks = arg::create_parse_blocks(args, &path, split);
     let body = &input.block;
 
+    if pipe_var.is_some() || pipe_output.is_some() {
+        if let Some(asyncness) = input.sig.asyncness {
+            return syn::Error::new(
+                asyncness.span(),
+                "Async commands can't take part in pipelines",
+            )
+            .into_compile_error()
+            .into();
+        }
+
+        let input_block = match pipe_var {
+            Some(pipe) => {
+                let ident = &pipe.pat;
+                let ty = &pipe.ty;
+                quote! {
+                    let #ident: #ty = clik::Pipe::from_value(input).map_err(|inner| {
+                        clik::error::Error::Pipe { path: (#path).iter().map(ToString::to_string).collect(), inner }
+                    })?;
+                }
+            }
+            None => quote! {
+                if input.is_some() {
+                    return Err(clik::error::Error::Pipe {
+                        path: (#path).iter().map(ToString::to_string).collect(),
+                        inner: clik::error::PipeError::InputNotAccepted
+                    }.into());
+                }
+            },
+        };
+        let output = match pipe_output {
+            Some(_) => quote!(output.map(|value| Some(Box::new(value) as clik::Value))),
+            None => quote!(output.map(|_| None)),
+        };
+
+        return TokenStream::from(quote! {
+            /// Construct a command struct containing the defined command
+            fn #fn_name<'a>() -> clik::Command<'a, #state_type, #error_type> {
+                clik::Command::new_pipe(stringify!(#command_name), #command_help, #new_fn_name)
+                    .with_args(vec![#(#arg_specs),*])
+                    .with_opts(vec![#(#opt_specs),*])
+                    #no_args
+            }
+
+            #[allow(clippy::redundant_closure_call)]
+            fn #new_fn_name(#context: #context_type, args: Vec<String>, input: Option<clik::Value>) -> Result<Option<clik::Value>, #error_type> {
+                // Receive the piped input
+                #input_block
+
+                // Parse all the command options and arguments
+                #opt_block
+                #arity_block
+                #(#arg_blocks)*
+                #bind_block
+
+                // The body of the function, producing the value for the next command
+                let output: #return_type = (move || -> #return_type #body)();
+                #output.map_err(Into::into)
+            }
+        });
+    }
+
     match input.sig.asyncness {
         // Synchronous function
         None => TokenStream::from(quote! {
             /// Construct a command struct containing the defined command
-            fn #fn_name<'a>() -> clik::Command<'a, #state_type> {
-                Command::new(stringify!(#command_name), stringify!(#command_help), #new_fn_name)
+            fn #fn_name<'a>() -> clik::Command<'a, #state_type, #error_type> {
+                clik::Command::new_context(stringify!(#command_name), #command_help, #new_fn_name)
+                    .with_args(vec![#(#arg_specs),*])
+                    .with_opts(vec![#(#opt_specs),*])
+                    #no_args
             }
 
-            fn #new_fn_name(state: &mut #state_type, args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+            fn #new_fn_name(#context: #context_type, args: Vec<String>) -> #return_type {
 
-                // Parse all the command arguments
+                // Parse all the command options and arguments
+                #opt_block
+                #arity_block
                 #(#arg_blocks)*
+                #bind_block
 
                 // The body of the function
                 #body
@@ -77,17 +206,30 @@ pub fn clik_command(attr: TokenStream, input: TokenStream) -> TokenStream {
         // Async function
         Some(_) => TokenStream::from(quote! {
             /// Construct a command struct containing the defined command
-            fn #fn_name<'a>() -> clik::Command<'a, #state_type> {
-                Command::new_async(stringify!(#command_name), stringify!(#command_help), clik::async_fn!(#state_type, #new_fn_name))
+            fn #fn_name<'a>() -> clik::Command<'a, #state_type, #error_type> {
+                clik::Command::new_async_context(stringify!(#command_name), #command_help, #new_fn_name)
+                    .with_args(vec![#(#arg_specs),*])
+                    .with_opts(vec![#(#opt_specs),*])
+                    #no_args
             }
 
-            async fn #new_fn_name(state: &mut #state_type, args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+            fn #new_fn_name<'c>(
+                #context: &'c mut clik::Context<'_, #state_type, #error_type>,
+                args: Vec<String>
+            ) -> ::std::pin::Pin<Box<dyn ::std::future::Future<Output = #return_type> + Send + 'c>> {
+                async fn #new_fn_name(#context: #context_type, args: Vec<String>) -> #return_type {
 
-                // Parse all the command arguments
-                #(#arg_blocks)*
+                    // Parse all the command options and arguments
+                    #opt_block
+                    #arity_block
+                    #(#arg_blocks)*
+                    #bind_block
 
-                // The body of the function
-                #body
+                    // The body of the function
+                    #body
+                }
+
+                Box::pin(#new_fn_name(#context, args))
             }
         }),
     }
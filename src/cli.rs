@@ -1,40 +1,138 @@
-use std::{error::Error, fmt::Display};
+use std::{any::Any, fmt::Display, fmt::Write, io::Write as _, process::ExitCode};
 
-use crate::{Command, CLI};
+use crate::{
+    command::{collect_candidates, sorted_commands, write_info_line},
+    error::{CommandError, Error, PipeError},
+    lexer,
+    parser::{parse_line, Chain, Pipeline},
+    repl::{EXIT_HELP, EXIT_NAMES},
+    suggest::suggest,
+    Command, Context, Value, CLI,
+};
 
-#[derive(Debug)]
-struct NoCommandError {
-    command: String,
-}
+/// The name of the built-in help command
+pub(crate) const HELP_NAME: &str = "help";
+/// The description of the built-in help command
+const HELP_HELP: &str = "Show all commands or the help page of a command";
 
-impl Display for NoCommandError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "No matching command has been found for '{}'",
-            self.command
-        )
+impl<'a, T: Send, E: CommandError> CLI<'a, T, E> {
+    /// Handle an input line. This line gets split up and then processed by all the commands.
+    /// A line can chain multiple commands with `;` (always run the next command),
+    /// `&&` (run the next command if the previous one succeeded) and `||` (run the next
+    /// command if the previous one failed). The first error that hasn't been handled
+    /// by an `||` is returned. The output of a command can be written to a file
+    /// with `> file` (replacing its contents) or `>> file` (appending to it)
+    /// instead of the output of the CLI (see [`CLI::set_output()`])
+    /// # Arguments
+    /// * `line` - The input line to use for execution
+    pub fn handle(&mut self, line: &str) -> Result<(), E> {
+        let mut chain = Chain::new();
+        for pipeline in parse_line(line).map_err(Error::from)? {
+            if chain.should_run(pipeline.link) {
+                chain.record(self.run_pipeline(&pipeline, line));
+            }
+        }
+        chain.finish()
     }
-}
-impl Error for NoCommandError {}
 
-impl<'a, T: Send> CLI<'a, T> {
-    /// Handle an input line. This line gets split up and then processed by all the commands
+    /// Handle arguments that have already been split up, e.g. the arguments of the process.
+    /// Errors are rendered through the error renderer (see [`CLI::set_error_renderer()`])
+    /// and printed to `stderr`. Nothing is done if there are no arguments
     /// # Arguments
-    /// * `line` - The input line to use for execution
-    pub fn handle(&mut self, line: &str) -> Result<(), Box<dyn Error>> {
-        let prompt = split_line(line);
-
-        if let Some(first) = prompt.first() {
-            if let Some(command) = self.commands.get(first) {
-                command.handle(&mut self.state, &prompt[1..prompt.len()])
-            } else {
-                Err(Box::new(NoCommandError {
-                    command: first.to_string(),
-                }))
+    /// * `args` - The command and its arguments, e.g. `std::env::args().skip(1)`
+    /// # Returns
+    /// The exit code for the process: `0` on success, `2` if the command was not found
+    /// or its arguments, options or piped value were not accepted and `1` for any other error.
+    /// Usage errors are only told apart for the default error type
+    pub fn handle_args(&mut self, args: impl IntoIterator<Item = impl AsRef<str>>) -> ExitCode {
+        let args: Vec<_> = args.into_iter().collect();
+        let prompt: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let line: Vec<String> = prompt.iter().map(|a| lexer::escape(a)).collect();
+
+        match self.dispatch(&prompt, &line.join(" "), None) {
+            Ok(_) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("{}", (self.render_error)(&e));
+                ExitCode::from(exit_code(&e))
             }
+        }
+    }
+
+    /// Run the commands of a pipeline, passing the value of every command to the next one
+    /// # Arguments
+    /// * `pipeline` - The pipeline to run
+    /// * `line` - The raw line the pipeline is part of
+    pub(crate) fn run_pipeline(&mut self, pipeline: &Pipeline, line: &str) -> Result<(), E> {
+        let previous = self.redirect_output(pipeline.redirect.as_ref())?;
+
+        // The output is restored even if a command fails, so stop at the first error
+        let mut result = Ok(());
+        let mut input = None;
+        for prompt in pipeline.prompts() {
+            match self.dispatch(&prompt, line, input) {
+                Ok(output) => input = output,
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+
+        let restored = self.restore_output(previous);
+        result?;
+        Ok(restored?)
+    }
+
+    /// Dispatch a split up line to the matching command
+    /// # Arguments
+    /// * `prompt` - The command and its arguments
+    /// * `line` - The raw line the command is part of
+    /// * `input` - The value piped into the command
+    fn dispatch(
+        &mut self,
+        prompt: &[&str],
+        line: &str,
+        input: Option<Value>,
+    ) -> Result<Option<Value>, E> {
+        match self.resolve(prompt, line)? {
+            Target::Nothing => Ok(None),
+            Target::Help if input.is_some() => Err(Error::Pipe {
+                path: vec![HELP_NAME.to_string()],
+                inner: PipeError::InputNotAccepted,
+            }
+            .into()),
+            Target::Help => self.print_help(&prompt[1..]).map(|_| None),
+            Target::Command(command, ctx) => command.call(ctx, prompt, input),
+        }
+    }
+
+    /// Resolve a split up line to its target, this is shared by the sync and async dispatch
+    /// so they report the same errors. The subcommands are resolved once the command is called
+    /// # Arguments
+    /// * `prompt` - The command and its arguments
+    /// * `line` - The raw line the command is part of
+    fn resolve<'s>(
+        &'s mut self,
+        prompt: &[&str],
+        line: &'s str,
+    ) -> Result<Target<'s, 'a, T, E>, Error> {
+        let Some(first) = prompt.first() else {
+            return Ok(Target::Nothing);
+        };
+
+        if let Some(command) = self.commands.get(*first) {
+            let ctx = Context::new(
+                &mut self.state,
+                self.output.as_mut(),
+                line,
+                &self.commands,
+                &mut self.variables,
+            );
+            Ok(Target::Command(command, ctx))
+        } else if self.is_builtin_help(first) {
+            Ok(Target::Help)
         } else {
-            Ok(())
+            Err(self.no_command_error(&prompt[..1]))
         }
     }
 
@@ -43,100 +141,688 @@ impl<'a, T: Send> CLI<'a, T> {
     /// * `line` - The input line to use for execution
     #[cfg_attr(nightly, doc(cfg(feature = "async")))]
     #[cfg(feature = "async")]
-    pub async fn handle_async(&mut self, line: &str) -> Result<(), Box<dyn Error>> {
-        let prompt = split_line(line);
-
-        if let Some(first) = prompt.first() {
-            if let Some(command) = self.commands.get(first) {
-                command
-                    .handle_async(&mut self.state, &prompt[1..prompt.len()])
-                    .await?
+    pub async fn handle_async(&mut self, line: &str) -> Result<(), E> {
+        let mut chain = Chain::new();
+        for pipeline in parse_line(line).map_err(Error::from)? {
+            if chain.should_run(pipeline.link) {
+                chain.record(self.run_pipeline_async(&pipeline, line).await);
             }
         }
+        chain.finish()
+    }
 
-        Ok(())
+    /// Run the commands of a pipeline asynchronously, passing the value of every command to the next one
+    /// # Arguments
+    /// * `pipeline` - The pipeline to run
+    /// * `line` - The raw line the pipeline is part of
+    #[cfg(feature = "async")]
+    async fn run_pipeline_async(&mut self, pipeline: &Pipeline, line: &str) -> Result<(), E> {
+        let previous = self.redirect_output(pipeline.redirect.as_ref())?;
+
+        // The output is restored even if a command fails, so stop at the first error
+        let mut result = Ok(());
+        let mut input = None;
+        for prompt in pipeline.prompts() {
+            match self.dispatch_async(&prompt, line, input).await {
+                Ok(output) => input = output,
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+
+        let restored = self.restore_output(previous);
+        result?;
+        Ok(restored?)
+    }
+
+    /// Dispatch a split up line to the matching command asynchronously
+    /// # Arguments
+    /// * `prompt` - The command and its arguments
+    /// * `line` - The raw line the command is part of
+    /// * `input` - The value piped into the command
+    #[cfg(feature = "async")]
+    async fn dispatch_async(
+        &mut self,
+        prompt: &[&str],
+        line: &str,
+        input: Option<Value>,
+    ) -> Result<Option<Value>, E> {
+        match self.resolve(prompt, line)? {
+            Target::Nothing => Ok(None),
+            Target::Help if input.is_some() => Err(Error::Pipe {
+                path: vec![HELP_NAME.to_string()],
+                inner: PipeError::InputNotAccepted,
+            }
+            .into()),
+            Target::Help => self.print_help(&prompt[1..]).map(|_| None),
+            Target::Command(command, ctx) => command.call_async(ctx, prompt, input).await,
+        }
     }
 
     /// Add a new command to this CLI
     /// # Arguments
     /// * `command` - The command to add
-    pub fn add_command(&mut self, command: Command<'a, T>) -> Option<Command<'_, T>> {
+    pub fn add_command(&mut self, command: Command<'a, T, E>) -> Option<Command<'_, T, E>> {
         self.commands.insert(command.name, command)
     }
+
+    /// The command registered with `name`
+    /// # Arguments
+    /// * `name` - The name of the command
+    pub fn command(&self, name: &str) -> Option<&Command<'a, T, E>> {
+        self.commands.get(name)
+    }
+
+    /// All the registered commands, sorted by their names
+    pub fn commands(&self) -> Vec<&Command<'a, T, E>> {
+        sorted_commands(&self.commands)
+    }
+
+    /// The state that gets represented to callbacks
+    pub fn state(&self) -> &T {
+        &self.state
+    }
+
+    /// The state that gets represented to callbacks, mutable
+    pub fn state_mut(&mut self) -> &mut T {
+        &mut self.state
+    }
+
+    /// Consume the CLI and take back its state
+    pub fn into_state(self) -> T {
+        self.state
+    }
+
+    /// Replace the state of this CLI
+    /// # Arguments
+    /// * `state` - The new state
+    /// # Returns
+    /// The previous state
+    pub fn replace_state(&mut self, state: T) -> T {
+        std::mem::replace(&mut self.state, state)
+    }
+
+    /// The value of a variable, variables are available to commands through their [`Context`]
+    /// # Arguments
+    /// * `name` - The name of the variable
+    pub fn variable(&self, name: &str) -> Option<&str> {
+        self.variables.get(name).map(String::as_str)
+    }
+
+    /// Set a variable that is available to commands through their [`Context`]
+    /// # Arguments
+    /// * `name` - The name of the variable
+    /// * `value` - The new value of the variable
+    /// # Returns
+    /// The previous value of the variable
+    pub fn set_variable(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Option<String> {
+        self.variables.insert(name.into(), value.into())
+    }
+
+    /// Remove a variable
+    /// # Arguments
+    /// * `name` - The name of the variable
+    /// # Returns
+    /// The value of the removed variable
+    pub fn remove_variable(&mut self, name: &str) -> Option<String> {
+        self.variables.remove(name)
+    }
+
+    /// Add the built-in `help [command [subcommand...]]` command to this CLI.
+    /// A command named `help` that is added by the application takes precedence
+    pub fn add_help_command(&mut self) {
+        self.builtin_help = true;
+    }
+
+    /// Render the help page of a command or an overview of all commands
+    /// # Arguments
+    /// * `path` - The path to the command to describe, an empty path renders the overview
+    pub fn help(&self, path: &[&str]) -> Result<String, Error> {
+        let mut res = String::new();
+
+        let written = match path.split_first() {
+            None => write!(res, "{self}"),
+            Some((first, [])) if self.is_builtin_help(first) => {
+                writeln!(
+                    res,
+                    "Usage: {HELP_NAME} [command [subcommand...]]\n\n{HELP_HELP}"
+                )
+            }
+            Some((first, [])) if self.is_builtin_exit(first) => {
+                writeln!(res, "Usage: {first}\n\n{EXIT_HELP}")
+            }
+            Some((first, rest)) => {
+                let no_command = || self.no_command_error(path);
+                let mut command = self.commands.get(first).ok_or_else(no_command)?;
+                for name in rest {
+                    command = command.subcommands.get(name).ok_or_else(no_command)?;
+                }
+                command.details(&mut res, &path.join(" "))
+            }
+        };
+
+        written.expect("Expected writing to a string to succeed");
+        Ok(res)
+    }
+
+    /// Find the deepest command that the leading words of a line refer to
+    /// # Arguments
+    /// * `words` - The words of the line
+    /// # Returns
+    /// The command and the number of words that form its path
+    #[cfg(feature = "rustyline")]
+    pub(crate) fn find_command<'s>(
+        &'s self,
+        words: &[&str],
+    ) -> Option<(&'s Command<'a, T, E>, usize)> {
+        let mut command = self.commands.get(*words.first()?)?;
+        let mut depth = 1;
+        while let Some(subcommand) = words.get(depth).and_then(|w| command.subcommands.get(*w)) {
+            command = subcommand;
+            depth += 1;
+        }
+        Some((command, depth))
+    }
+
+    /// Create the error for a path that does not lead to a command,
+    /// suggesting similar commands and subcommands
+    /// # Arguments
+    /// * `path` - The path that has been typed
+    fn no_command_error(&self, path: &[&str]) -> Error {
+        // Find the deepest command that is still known, the name after it is not
+        let Some(mut command) = self.commands.get(path[0]).filter(|_| path.len() > 1) else {
+            let mut candidates = Vec::new();
+            if self.is_builtin_help(HELP_NAME) {
+                candidates.push((HELP_NAME.to_string(), HELP_NAME));
+            }
+            for name in EXIT_NAMES.into_iter().filter(|n| self.is_builtin_exit(n)) {
+                candidates.push((name.to_string(), name));
+            }
+            collect_candidates(&self.commands, "", &mut candidates);
+
+            return Error::UnknownCommand {
+                command: path[0].to_string(),
+                suggestions: suggest(path[0], candidates),
+            };
+        };
+
+        let mut known = 1;
+        while let Some(subcommand) = path.get(known).and_then(|w| command.subcommands.get(*w)) {
+            command = subcommand;
+            known += 1;
+        }
+        command.unknown_subcommand(&path[..known], path[known])
+    }
+
+    /// Print the help page for the built-in help command to the output
+    /// # Arguments
+    /// * `path` - The arguments supplied to the help command
+    fn print_help(&mut self, path: &[&str]) -> Result<(), E> {
+        let help = self.help(path)?;
+        self.output.write_all(help.as_bytes())?;
+        Ok(())
+    }
+
+    /// Checks if `name` refers to the built-in help command
+    /// # Arguments
+    /// * `name` - The name of the command
+    pub(crate) fn is_builtin_help(&self, name: &str) -> bool {
+        self.builtin_help && name == HELP_NAME && !self.commands.contains_key(HELP_NAME)
+    }
 }
 
-impl<'a, T: Send> Display for CLI<'_, T> {
+impl<T: Send, E: CommandError> Display for CLI<'_, T, E> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Available commands: \n")?;
 
-        for c in &self.commands {
-            c.1.info(f, 0)?;
+        for c in sorted_commands(&self.commands) {
+            c.info(f, 0)?;
+        }
+
+        if self.is_builtin_help(HELP_NAME) {
+            write_info_line(f, 0, HELP_NAME, HELP_HELP)?;
+        }
+        for name in EXIT_NAMES.into_iter().filter(|n| self.is_builtin_exit(n)) {
+            write_info_line(f, 0, name, EXIT_HELP)?;
         }
 
         Ok(())
     }
 }
 
-/// Splits a line into seperate parts according to following rules:
-/// - Split at ' ' (space)
-/// - If a string is quoted ("), there will be no split at the space
+/// The target a split up line resolves to
+enum Target<'s, 'a, T, E> {
+    /// The line is empty
+    Nothing,
+    /// The built-in help command
+    Help,
+    /// A command and the context to call it in
+    Command(&'s Command<'a, T, E>, Context<'s, T, E>),
+}
+
+/// Maps an error to the exit code of the process, see [`CLI::handle_args()`]
 /// # Arguments
-/// * `line` - The line to split up
-fn split_line(line: &str) -> Vec<&str> {
-    let mut in_string = false;
-    let mut split: Vec<&str> = Vec::new();
-    let mut start: usize = 0;
-
-    for (i, c) in line.chars().enumerate() {
-        if c == '"' {
-            if !in_string {
-                start += 1
-            } else {
-                start -= 1
-            }
-            in_string = !in_string;
-            continue;
-        }
+/// * `err` - The error to map
+fn exit_code<E: 'static>(err: &E) -> u8 {
+    // Only the default error type can be inspected
+    let Some(err) = (err as &dyn Any).downcast_ref::<Box<dyn std::error::Error>>() else {
+        return 1;
+    };
 
-        if c == ' ' && !in_string {
-            split.push(&line[start..i]);
-            start = i + 1;
-        }
+    match err.downcast_ref::<Error>() {
+        Some(Error::AsyncCommand { .. } | Error::Lex(_)) | None => 1,
+        Some(_) => 2,
     }
-    split.push(&line[start..line.len()]);
-
-    split.retain(|s| s != &"");
-    split
-        .iter()
-        .map(|s| s.trim_start_matches('"').trim_end_matches('"'))
-        .collect()
 }
 
 #[cfg(test)]
 mod tests {
+    use std::error::Error;
+
     use super::*;
+    #[cfg(feature = "async")]
+    use crate::test_support::block_on;
+    use crate::{error::LexError, Pipe};
+
+    type PipeResult = Result<Option<Value>, Box<dyn Error>>;
+
+    fn noop(_: &mut (), _: Vec<String>) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// Creates a CLI with a `tag` command with the `add` and `remove` subcommands
+    fn tag_cli<'a>() -> CLI<'a, ()> {
+        let mut tag = Command::new("tag", "Manage tags", noop);
+        tag.add_subcommand(Command::new("remove", "Remove a tag", noop));
+        tag.add_subcommand(Command::new("add", "Add a tag", noop));
+
+        let mut cli = CLI::new(());
+        cli.add_command(tag);
+        cli.add_command(Command::new("echo", "Echo something", noop));
+        cli
+    }
+
+    #[test]
+    fn test_handle_lex_error() {
+        let mut cli = CLI::new(());
+        let err = cli
+            .handle("echo \"unterminated")
+            .expect_err("Expected lex error");
+        assert!(matches!(
+            err.downcast_ref::<crate::error::Error>(),
+            Some(crate::error::Error::Lex(LexError::UnterminatedQuote {
+                quote: '"',
+                position: 5
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_handle_chain() {
+        fn push(state: &mut Vec<String>, args: Vec<String>) -> Result<(), Box<dyn Error>> {
+            state.push(args.join(" "));
+            Ok(())
+        }
+        fn fail(_: &mut Vec<String>, _: Vec<String>) -> Result<(), Box<dyn Error>> {
+            Err("failed".into())
+        }
+
+        let mut cli = CLI::new(Vec::new());
+        cli.add_command(Command::new("push", "Push the arguments", push));
+        cli.add_command(Command::new("fail", "Always fails", fail));
+
+        cli.handle("push a && push 'b && c'; push d;push e")
+            .unwrap();
+        assert_eq!(cli.state, vec!["a", "b && c", "d", "e"]);
+
+        cli.state.clear();
+        cli.handle("fail || push a; push b && fail || push c")
+            .unwrap();
+        assert_eq!(cli.state, vec!["a", "b", "c"]);
+
+        cli.state.clear();
+        let err = cli
+            .handle("fail && push a; nothing; push b")
+            .expect_err("Expected chain to fail");
+        assert_eq!(err.to_string(), "failed");
+        assert_eq!(cli.state, vec!["b"]);
+
+        let err = cli.handle("push a &&").expect_err("Expected lex error");
+        assert!(matches!(
+            err.downcast_ref::<crate::error::Error>(),
+            Some(crate::error::Error::Lex(_))
+        ));
+        assert_eq!(cli.state, vec!["b"]);
+    }
+
+    #[test]
+    fn test_handle_pipeline() {
+        fn list(_: &mut Context<usize>, _: Vec<String>, _: Option<Value>) -> PipeResult {
+            Ok(Some(Box::new(vec![1, 2, 3, 4])))
+        }
+        fn even(_: &mut Context<usize>, _: Vec<String>, input: Option<Value>) -> PipeResult {
+            let values = Pipe::<Vec<i32>>::from_value(input)?
+                .into_inner()
+                .unwrap_or_default();
+            Ok(Some(Box::new(
+                values
+                    .into_iter()
+                    .filter(|v| v % 2 == 0)
+                    .collect::<Vec<_>>(),
+            )))
+        }
+        fn count(ctx: &mut Context<usize>, _: Vec<String>, input: Option<Value>) -> PipeResult {
+            *ctx.state_mut() = Pipe::<Vec<i32>>::from_value(input)?.into_iter().count();
+            Ok(None)
+        }
+        fn name(_: &mut Context<usize>, _: Vec<String>, _: Option<Value>) -> PipeResult {
+            Ok(Some(Box::new("name".to_string())))
+        }
+        fn noop(_: &mut usize, _: Vec<String>) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+
+        let mut cli = CLI::new(0);
+        cli.add_command(Command::new_pipe("list", "List numbers", list));
+        cli.add_command(Command::new_pipe("even", "Keep even numbers", even));
+        cli.add_command(Command::new_pipe("count", "Count the values", count));
+        cli.add_command(Command::new_pipe("name", "Produce a name", name));
+        cli.add_command(Command::new("noop", "Do nothing", noop));
+
+        cli.handle("list | count").unwrap();
+        assert_eq!(cli.state, 4);
+        cli.handle("list|even | count").unwrap();
+        assert_eq!(cli.state, 2);
+        cli.handle("count").unwrap();
+        assert_eq!(cli.state, 0);
+        cli.handle("noop | count").unwrap();
+        assert_eq!(cli.state, 0);
+
+        let err = cli.handle("list | noop").expect_err("Expected pipe error");
+        assert!(matches!(
+            err.downcast_ref::<crate::error::Error>(),
+            Some(crate::error::Error::Pipe {
+                path,
+                inner: PipeError::InputNotAccepted
+            }) if path == &["noop"]
+        ));
+        // The callback itself returns the error of the piped value
+        let err = cli.handle("name | even").expect_err("Expected pipe error");
+        assert!(matches!(
+            err.downcast_ref::<PipeError>(),
+            Some(PipeError::WrongType { .. })
+        ));
+        let err = cli
+            .handle("list | nothing")
+            .expect_err("Expected unknown command");
+        assert!(matches!(
+            err.downcast_ref::<crate::error::Error>(),
+            Some(crate::error::Error::UnknownCommand { command, .. }) if command == "nothing"
+        ));
+    }
+
+    #[test]
+    fn test_help_overview() {
+        let mut cli = tag_cli();
+        cli.add_help_command();
+
+        let help = cli.help(&[]).unwrap();
+        let lines: Vec<&str> = help.lines().collect();
+        assert_eq!(lines[0], "Available commands: ");
+        assert!(lines[2].starts_with("|-- echo ") && lines[2].ends_with(" Echo something"));
+        assert!(lines[3].starts_with("|-- tag ") && lines[3].ends_with(" Manage tags"));
+        assert!(lines[4].starts_with("|  |-- add ") && lines[4].ends_with(" Add a tag"));
+        assert!(lines[5].starts_with("|  |-- remove "));
+        assert!(lines[6].starts_with("|-- help ") && lines[6].ends_with(HELP_HELP));
+        assert!(lines[7].starts_with("|-- exit ") && lines[7].ends_with(EXIT_HELP));
+        assert!(lines[8].starts_with("|-- quit "));
+
+        let help = cli.help(&["quit"]).unwrap();
+        assert_eq!(help, format!("Usage: quit\n\n{EXIT_HELP}\n"));
+    }
+
+    #[test]
+    fn test_help_details() {
+        let cli = tag_cli();
+
+        let help = cli.help(&["tag"]).unwrap();
+        let lines: Vec<&str> = help.lines().collect();
+        assert_eq!(
+            lines[..5],
+            ["Usage: tag", "", "Manage tags", "", "Subcommands:"]
+        );
+        assert!(lines[5].starts_with("|-- add "));
+        assert!(lines[6].starts_with("|-- remove "));
+
+        let help = cli.help(&["tag", "add"]).unwrap();
+        assert_eq!(help, "Usage: tag add\n\nAdd a tag\n");
+    }
 
     #[test]
-    fn test_no_split() {
-        let line = "help";
-        assert_eq!(split_line(line), vec!["help"]);
+    fn test_help_unknown_command() {
+        let cli = tag_cli();
+
+        let err = cli.help(&["tag", "rename"]).expect_err("Expected error");
+        assert_eq!(
+            err.to_string(),
+            "No matching command has been found for 'tag rename'"
+        );
     }
 
     #[test]
-    fn test_split_spaces() {
-        let line = "help cmd";
-        assert_eq!(split_line(line), vec!["help", "cmd"])
+    fn test_builtin_help() {
+        let mut cli = tag_cli();
+        assert!(cli.handle("help").is_err());
+
+        cli.add_help_command();
+        assert!(cli.handle("help").is_ok());
+        assert!(cli.handle("help tag add").is_ok());
+        assert!(cli.handle("help help").is_ok());
+        assert!(cli.handle("help nothing").is_err());
     }
 
     #[test]
-    fn test_quotes_no_split() {
-        let line = "\"help\"";
-        assert_eq!(split_line(line), vec!["help"]);
+    fn test_handle_args() {
+        fn fail(_: &mut (), _: Vec<String>) -> Result<(), Box<dyn Error>> {
+            Err("failed".into())
+        }
+
+        let mut cli = tag_cli();
+        cli.add_command(Command::new("fail", "Always fails", fail));
+        cli.add_help_command();
+
+        assert_eq!(cli.handle_args(["tag", "add", "a b"]), ExitCode::SUCCESS);
+        assert_eq!(cli.handle_args(Vec::<String>::new()), ExitCode::SUCCESS);
+        assert_eq!(cli.handle_args(["help", "echo"]), ExitCode::SUCCESS);
+        assert_eq!(cli.handle_args(["ehco"]), ExitCode::from(2));
+        assert_eq!(cli.handle_args(["fail"]), ExitCode::from(1));
     }
 
     #[test]
-    fn test_quotes_split_spaces() {
-        let line = "\"help\" \"cmd\"";
-        assert_eq!(split_line(line), vec!["help", "cmd"]);
+    fn test_closures() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut calls = 0;
+        let mut cli = CLI::new(0);
+        cli.add_command(Command::from_closure(
+            "send",
+            "Send the arguments",
+            move |state: &mut i32, args| {
+                calls += 1;
+                *state = calls;
+                sender.send(args.join(" "))?;
+                Ok(())
+            },
+        ));
+
+        cli.handle("send a b; send c").unwrap();
+        assert_eq!(cli.state, 2);
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), ["a b", "c"]);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_closures() {
+        let prefix = String::from("got ");
+        let mut cli = CLI::new(Vec::new());
+        cli.add_command(Command::from_async_closure(
+            "push",
+            "Push the arguments",
+            move |state: &mut Vec<String>, args| {
+                let prefix = prefix.clone();
+                Box::pin(async move {
+                    state.push(format!("{prefix}{}", args.join(" ")));
+                    Ok(())
+                })
+            },
+        ));
+
+        block_on(cli.handle_async("push a; push b")).unwrap();
+        assert_eq!(cli.state, ["got a", "got b"]);
+        assert!(cli.handle("push c").is_err());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_handle_async_consistency() {
+        // The argument errors of generated commands are compared in `tests/codegen.rs`
+        fn set(ctx: &mut Context<usize>, args: Vec<String>) -> Result<(), Box<dyn Error>> {
+            *ctx.state_mut() = args.len();
+            Ok(())
+        }
+
+        fn fail(_: &mut usize, _: Vec<String>) -> Result<(), Box<dyn Error>> {
+            Err("failed".into())
+        }
+
+        /// Handles a line through both paths, the results have to be identical
+        fn handle(cli: &mut CLI<usize>, line: &str) -> Result<usize, String> {
+            let sync = cli.handle(line).map(|_| cli.state);
+            cli.state = 0;
+            let asynchronous = block_on(cli.handle_async(line)).map(|_| cli.state);
+            cli.state = 0;
+
+            let sync = sync.map_err(|e| format!("{e:?}"));
+            let asynchronous = asynchronous.map_err(|e| format!("{e:?}"));
+            assert_eq!(
+                sync, asynchronous,
+                "Expected identical results for '{line}'"
+            );
+            sync
+        }
+
+        let mut cli = CLI::new(0);
+        let mut tag = Command::new("tag", "Manage tags", fail).without_args();
+        tag.add_subcommand(Command::new_context("set", "Set the number", set));
+        cli.add_command(tag);
+        cli.add_command(Command::new_context("set", "Set the number", set));
+        cli.add_command(Command::new("fail", "Always fail", fail));
+        cli.add_help_command();
+        cli.set_output(std::io::sink());
+
+        let mut error = |line| handle(&mut cli, line).expect_err("Expected an error");
+        assert!(error("sett 1").starts_with("UnknownCommand"));
+        assert!(error("help tag sett").starts_with("UnknownSubcommand"));
+        assert!(error("set \"1").starts_with("Lex"));
+        assert!(error("fail").contains("failed"));
+        assert!(error("tag sett").starts_with("UnknownSubcommand"));
+
+        assert_eq!(handle(&mut cli, ""), Ok(0));
+        assert_eq!(handle(&mut cli, "help tag"), Ok(0));
+        assert_eq!(handle(&mut cli, "tag set 3 4"), Ok(2));
+        assert_eq!(handle(&mut cli, "fail || set 4"), Ok(1));
+    }
+
+    #[test]
+    fn test_state() {
+        let mut cli = CLI::new(1);
+        assert_eq!(*cli.state(), 1);
+
+        *cli.state_mut() += 1;
+        assert_eq!(cli.replace_state(5), 2);
+        assert_eq!(cli.into_state(), 5);
+    }
+
+    #[test]
+    fn test_commands() {
+        let cli = tag_cli();
+
+        let names: Vec<&str> = cli.commands().iter().map(|c| c.name()).collect();
+        assert_eq!(names, vec!["echo", "tag"]);
+
+        let tag = cli.command("tag").expect("Expected tag command");
+        assert_eq!(tag.help(), "Manage tags");
+        let names: Vec<&str> = tag.subcommands().iter().map(|c| c.name()).collect();
+        assert_eq!(names, vec!["add", "remove"]);
+        assert_eq!(tag.subcommand("add").map(|c| c.help()), Some("Add a tag"));
+        assert!(cli.command("add").is_none());
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let mut cli = tag_cli();
+        cli.add_help_command();
+
+        fn suggestions(err: Box<dyn Error>) -> Vec<String> {
+            match err.downcast::<crate::error::Error>().map(|e| *e) {
+                Ok(crate::error::Error::UnknownCommand { suggestions, .. }) => suggestions,
+                other => panic!("Expected unknown command, got {other:?}"),
+            }
+        }
+
+        let err = cli.handle("ehco hi").expect_err("Expected unknown command");
+        assert_eq!(
+            err.to_string(),
+            "No matching command 'ehco' — did you mean 'echo'?"
+        );
+        assert_eq!(suggestions(err), vec!["echo"]);
+
+        let err = cli.handle("ad").expect_err("Expected unknown command");
+        assert_eq!(suggestions(err), vec!["tag add"]);
+
+        let err = cli.handle("hlep").expect_err("Expected unknown command");
+        assert_eq!(suggestions(err), vec!["help"]);
+
+        let err = cli.handle("exti").expect_err("Expected unknown command");
+        assert_eq!(suggestions(err), vec!["exit"]);
+
+        // Other words are arguments of the command, unless it takes none
+        cli.handle("tag remov").unwrap();
+        let mut tag = Command::new("tag", "Manage tags", noop).without_args();
+        tag.add_subcommand(Command::new("remove", "Remove a tag", noop));
+        cli.add_command(tag);
+
+        let err = cli
+            .handle("tag remov")
+            .expect_err("Expected unknown subcommand");
+        assert_eq!(
+            err.to_string(),
+            "No matching command 'tag remov' — did you mean 'tag remove'?"
+        );
+
+        let err = cli
+            .help(&["tag", "remov"])
+            .expect_err("Expected unknown subcommand");
+        assert_eq!(
+            err.to_string(),
+            "No matching command 'tag remov' — did you mean 'tag remove'?"
+        );
+        match err {
+            crate::error::Error::UnknownSubcommand {
+                path,
+                subcommand,
+                suggestions,
+            } => {
+                assert_eq!(path, vec!["tag"]);
+                assert_eq!(subcommand, "remov");
+                assert_eq!(suggestions, vec!["tag remove"]);
+            }
+            other => panic!("Expected unknown subcommand, got {other:?}"),
+        }
     }
 }
@@ -1,81 +1,421 @@
-use std::{error::Error, fmt::Display};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
 
-#[cfg_attr(nightly, doc(cfg(feature = "async")))]
-#[cfg(feature = "async")]
-use async_recursion::async_recursion;
+use crate::{
+    error::{CommandError, Error, PipeError},
+    suggest::suggest,
+    ArgSpec, Command, Context, FnType, OptSpec, Value,
+};
 
-use crate::{Command, FnType};
-
-#[derive(Debug)]
-struct AsyncHandleError {}
-
-impl Display for AsyncHandleError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Tried to use async callback with sync handler!")
+impl<'a, T: Send, E: CommandError> Command<'a, T, E> {
+    /// Handle a prompt and see if there is some match. If there is an `async` callback, this will fail
+    /// # Arguments
+    /// * `state` - The state to provide to the callback if a match is found
+    /// * `prompt` - A collection of strings that form the prompt
+    pub fn handle(&self, state: &mut T, prompt: &[&str]) -> Result<(), E> {
+        self.handle_piped(state, prompt, None).map(|_| ())
     }
-}
-impl Error for AsyncHandleError {}
 
-impl<'a, T: Send> Command<'a, T> {
-    /// Handle a prompt and see if there is some match. If there is an `async` callback, this will fail
+    /// Handle a prompt that is part of a pipeline and see if there is some match.
+    /// If there is an `async` callback or a value is piped into a command that
+    /// does not accept one, this will fail. Callbacks receiving a context
+    /// write to `stdout` and see no variables
     /// # Arguments
     /// * `state` - The state to provide to the callback if a match is found
     /// * `prompt` - A collection of strings that form the prompt
-    pub fn handle(&self, state: &mut T, prompt: &[&str]) -> Result<(), Box<dyn Error>> {
-        if let Some(cmd) = prompt.first() {
-            if let Some(subcommand) = self.subcommands.get(cmd) {
-                return subcommand.handle(state, &prompt[1..prompt.len()]);
-            }
-        }
+    /// * `input` - The value of the previous command in the pipeline
+    /// # Returns
+    /// The value for the next command in the pipeline
+    pub fn handle_piped(
+        &self,
+        state: &mut T,
+        prompt: &[&str],
+        input: Option<Value>,
+    ) -> Result<Option<Value>, E> {
+        let prompt: Vec<&str> = std::iter::once(self.name)
+            .chain(prompt.iter().copied())
+            .collect();
+        let line = prompt.join(" ");
+        let mut output = io::stdout();
+        let commands = HashMap::new();
+        let mut variables = HashMap::new();
+
+        let ctx = Context::new(state, &mut output, &line, &commands, &mut variables);
+        self.call(ctx, &prompt, input)
+    }
+
+    /// Call the callback of the command matching a prompt
+    /// # Arguments
+    /// * `ctx` - The context to invoke the command in
+    /// * `prompt` - The prompt, starting with the name of this command
+    /// * `input` - The value of the previous command in the pipeline
+    /// # Returns
+    /// The value for the next command in the pipeline
+    pub(crate) fn call<'c>(
+        &'c self,
+        ctx: Context<'c, T, E>,
+        prompt: &'c [&'c str],
+        input: Option<Value>,
+    ) -> Result<Option<Value>, E> {
+        let (command, args) = self.resolve(prompt)?;
+        let mut ctx = ctx.with_command(&prompt[..prompt.len() - args.len()], &command.args);
+        let args = args.iter().map(|a| a.to_string()).collect();
 
-        match self.callback {
-            FnType::Sync(f) => (f)(state, prompt.iter().map(|a| a.to_string()).collect()),
+        match &command.callback {
+            FnType::Pipe(f) => (f)(&mut ctx, args, input),
+            _ if input.is_some() => Err(Error::Pipe {
+                path: ctx.path().iter().map(ToString::to_string).collect(),
+                inner: PipeError::InputNotAccepted,
+            }
+            .into()),
+            FnType::Sync(f) => (f)(ctx.into_state(), args).map(|_| None),
+            FnType::Closure(f) => (lock(f))(ctx.into_state(), args).map(|_| None),
+            FnType::Context(f) => (f)(&mut ctx, args).map(|_| None),
             #[cfg_attr(nightly, doc(cfg(feature = "async")))]
             #[cfg(feature = "async")]
-            FnType::Async(_) => Err(Box::new(AsyncHandleError {})),
+            FnType::Async(_) | FnType::AsyncClosure(_) | FnType::AsyncContext(_) => {
+                Err(Error::AsyncCommand {
+                    path: ctx.path().iter().map(ToString::to_string).collect(),
+                }
+                .into())
+            }
         }
     }
 
-    /// Handle a prompt asynchronously and see if there is some match
+    /// Handle a prompt asynchronously and see if there is some match.
+    /// Callbacks receiving a context write to `stdout` and see no variables
     /// # Arguments
     /// * `state` - The state to provide to the callback if a match is found
     /// * `prompt` - A collection of strings that form the prompt
     #[cfg_attr(nightly, doc(cfg(feature = "async")))]
     #[cfg(feature = "async")]
-    #[async_recursion]
-    pub async fn handle_async(&self, state: &mut T, prompt: &[&str]) -> Result<(), Box<dyn Error>> {
-        if let Some(cmd) = prompt.first() {
-            if let Some(subcommand) = self.subcommands.get(cmd) {
-                return subcommand
-                    .handle_async(state, &prompt[1..prompt.len()])
-                    .await;
+    pub async fn handle_async(&self, state: &mut T, prompt: &[&str]) -> Result<(), E> {
+        let prompt: Vec<&str> = std::iter::once(self.name)
+            .chain(prompt.iter().copied())
+            .collect();
+        let line = prompt.join(" ");
+        let mut output = io::stdout();
+        let commands = HashMap::new();
+        let mut variables = HashMap::new();
+
+        let ctx = Context::new(state, &mut output, &line, &commands, &mut variables);
+        self.call_async(ctx, &prompt, None).await.map(|_| ())
+    }
+
+    /// Call the callback of the command matching a prompt asynchronously
+    /// # Arguments
+    /// * `ctx` - The context to invoke the command in
+    /// * `prompt` - The prompt, starting with the name of this command
+    /// * `input` - The value of the previous command in the pipeline
+    /// # Returns
+    /// The value for the next command in the pipeline
+    #[cfg(feature = "async")]
+    pub(crate) async fn call_async<'c>(
+        &'c self,
+        ctx: Context<'c, T, E>,
+        prompt: &'c [&'c str],
+        input: Option<Value>,
+    ) -> Result<Option<Value>, E> {
+        let (command, args) = self.resolve(prompt)?;
+        let mut ctx = ctx.with_command(&prompt[..prompt.len() - args.len()], &command.args);
+        let args = args.iter().map(|a| a.to_string()).collect();
+
+        match &command.callback {
+            FnType::Pipe(f) => (f)(&mut ctx, args, input),
+            _ if input.is_some() => Err(Error::Pipe {
+                path: ctx.path().iter().map(ToString::to_string).collect(),
+                inner: PipeError::InputNotAccepted,
+            }
+            .into()),
+            FnType::Sync(f) => (f)(ctx.into_state(), args).map(|_| None),
+            FnType::Async(f) => (f)(ctx.into_state(), args).await.map(|_| None),
+            FnType::Closure(f) => (lock(f))(ctx.into_state(), args).map(|_| None),
+            FnType::AsyncClosure(f) => {
+                // The closure is only locked while it creates the future
+                let future = (lock(f))(ctx.into_state(), args);
+                future.await.map(|_| None)
             }
+            FnType::Context(f) => (f)(&mut ctx, args).map(|_| None),
+            FnType::AsyncContext(f) => (f)(&mut ctx, args).await.map(|_| None),
         }
+    }
+
+    /// Walk down the subcommands matching a prompt. A word that matches none of the subcommands
+    /// is an argument, unless the command takes no arguments (see [`Command::without_args()`]),
+    /// then it is an unknown subcommand
+    /// # Arguments
+    /// * `prompt` - The prompt, starting with the name of this command
+    /// # Returns
+    /// The matching command and the remaining arguments for it
+    fn resolve<'p, 'w>(&self, prompt: &'p [&'w str]) -> Result<(&Self, &'p [&'w str]), Error> {
+        let mut command = self;
+        let mut depth = 1;
+        while let Some(word) = prompt.get(depth) {
+            if let Some(subcommand) = command.subcommands.get(*word) {
+                command = subcommand;
+                depth += 1;
+            } else if command.no_args && !command.subcommands.is_empty() {
+                return Err(command.unknown_subcommand(&prompt[..depth], word));
+            } else {
+                break;
+            }
+        }
+        Ok((command, &prompt[depth..]))
+    }
 
-        match self.callback {
-            FnType::Sync(f) => (f)(state, prompt.iter().map(|a| a.to_string()).collect()),
-            FnType::Async(f) => (f)(state, prompt.iter().map(|a| a.to_string()).collect()).await,
+    /// Create the error for a word that matches none of the subcommands of this command,
+    /// suggesting similar subcommands
+    /// # Arguments
+    /// * `path` - The path of this command as it has been typed
+    /// * `subcommand` - The word that matches none of the subcommands
+    pub(crate) fn unknown_subcommand(&self, path: &[&str], subcommand: &str) -> Error {
+        let mut candidates = Vec::new();
+        collect_candidates(&self.subcommands, &path.join(" "), &mut candidates);
+
+        Error::UnknownSubcommand {
+            path: path.iter().map(ToString::to_string).collect(),
+            subcommand: subcommand.to_string(),
+            suggestions: suggest(subcommand, candidates),
         }
     }
 
     /// Add a new subcommand to this command
     /// # Arguments
     /// * `command` - The command to add
-    pub fn add_subcommand(&mut self, command: Command<'a, T>) -> Option<Command<'_, T>> {
+    pub fn add_subcommand(&mut self, command: Command<'a, T, E>) -> Option<Command<'_, T, E>> {
         self.subcommands.insert(command.name, command)
     }
 
+    /// The subcommand registered with `name`
+    /// # Arguments
+    /// * `name` - The name of the subcommand
+    pub fn subcommand(&self, name: &str) -> Option<&Command<'a, T, E>> {
+        self.subcommands.get(name)
+    }
+
+    /// All the registered subcommands, sorted by their names
+    pub fn subcommands(&self) -> Vec<&Command<'a, T, E>> {
+        sorted_commands(&self.subcommands)
+    }
+
+    /// The name of this command, as typed into the CLI
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// The help string describing this command
+    pub fn help(&self) -> &'a str {
+        self.help
+    }
+
+    /// Describe the arguments this command expects
+    /// # Arguments
+    /// * `args` - The argument descriptions, ordered by their position
+    pub fn with_args(mut self, args: Vec<ArgSpec<'a>>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// The arguments this command expects, ordered by their position
+    pub fn args(&self) -> &[ArgSpec<'a>] {
+        &self.args
+    }
+
+    /// Describe the named options this command accepts
+    /// # Arguments
+    /// * `opts` - The option descriptions
+    pub fn with_opts(mut self, opts: Vec<OptSpec<'a>>) -> Self {
+        self.opts = opts;
+        self
+    }
+
+    /// The named options this command accepts
+    pub fn opts(&self) -> &[OptSpec<'a>] {
+        &self.opts
+    }
+
+    /// Declare that this command takes no arguments, so a word that matches none of its
+    /// subcommands is reported as an unknown subcommand instead of being passed to the callback.
+    /// The `clik_command` macro declares this for functions without arguments and options
+    pub fn without_args(mut self) -> Self {
+        self.no_args = true;
+        self
+    }
+
+    /// The usage line of this command, e.g. `echo [-v|--verbose] <number: i32>`
+    pub fn usage(&self) -> String {
+        usage_line(self.name, &self.args, &self.opts)
+    }
+
     /// Provide some information about the command
-    pub fn info(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
-        let indent = "|  ".repeat(depth);
-        let string = format!("{}|-- {} ", indent, self.name);
+    pub fn info(&self, f: &mut dyn std::fmt::Write, depth: usize) -> std::fmt::Result {
+        write_info_line(f, depth, &self.usage(), self.help)?;
+
+        for c in sorted_commands(&self.subcommands) {
+            c.info(f, depth + 1)?;
+        }
+
+        Ok(())
+    }
 
-        writeln!(f, "{:.<35} {}", string, self.help)?;
+    /// Provide a detailed description of the command and its subcommands
+    /// # Arguments
+    /// * `f` - The writer to write the description to
+    /// * `path` - The full path to this command, as typed into the CLI
+    pub fn details(&self, f: &mut dyn std::fmt::Write, path: &str) -> std::fmt::Result {
+        writeln!(f, "Usage: {}", usage_line(path, &self.args, &self.opts))?;
+        writeln!(f)?;
+        writeln!(f, "{}", self.help)?;
+
+        if !self.args.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Arguments:")?;
+            for arg in &self.args {
+                let help = match arg.default {
+                    Some(default) => {
+                        format!("{} (default: {default})", arg.help.unwrap_or_default())
+                    }
+                    None => arg.help.unwrap_or_default().to_string(),
+                };
+                writeln!(
+                    f,
+                    "  {:<30} {}",
+                    format!("{}: {}", arg.name, arg.ty),
+                    help.trim_start()
+                )?;
+            }
+        }
+
+        if !self.opts.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Options:")?;
+            for opt in &self.opts {
+                let names = opt.names().join(", ");
+                let names = match opt.flag {
+                    true => names,
+                    false => format!("{names} <{}>", opt.ty),
+                };
+                writeln!(f, "  {:<30} {}", names, opt.help.unwrap_or_default())?;
+            }
+        }
 
-        for c in &self.subcommands {
-            c.1.info(f, depth + 1)?;
+        if !self.subcommands.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Subcommands:")?;
+            for c in sorted_commands(&self.subcommands) {
+                c.info(f, 0)?;
+            }
         }
 
         Ok(())
     }
 }
+
+impl Display for ArgSpec<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let variadic = if self.variadic { "..." } else { "" };
+        if self.required {
+            write!(f, "<{}: {}{variadic}>", self.name, self.ty)
+        } else if let Some(default) = self.default {
+            write!(f, "[{}: {} = {default}]", self.name, self.ty)
+        } else {
+            write!(f, "[{}: {}{variadic}]", self.name, self.ty)
+        }
+    }
+}
+
+impl OptSpec<'_> {
+    /// The names of this option as they are typed, e.g. `["-n", "--count"]`
+    pub fn names(&self) -> Vec<String> {
+        let short = self.short.map(|short| format!("-{short}"));
+        let long = self.long.map(|long| format!("--{long}"));
+        short.into_iter().chain(long).collect()
+    }
+}
+
+impl Display for OptSpec<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = self.names().join("|");
+        if self.flag {
+            write!(f, "[{names}]")
+        } else {
+            write!(f, "[{names} <{}>]", self.ty)
+        }
+    }
+}
+
+/// Locks the closure of a command, a closure that panicked can still be called
+/// # Arguments
+/// * `closure` - The closure to lock
+fn lock<F: ?Sized>(closure: &Mutex<Box<F>>) -> MutexGuard<'_, Box<F>> {
+    closure.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Creates the usage line for a command, e.g. `echo [-v|--verbose] <number: i32>`
+/// # Arguments
+/// * `path` - The path to the command
+/// * `args` - The arguments the command expects
+/// * `opts` - The options the command accepts
+fn usage_line(path: &str, args: &[ArgSpec], opts: &[OptSpec]) -> String {
+    let mut line = path.to_string();
+    for opt in opts {
+        line.push_str(&format!(" {opt}"));
+    }
+    for arg in args {
+        line.push_str(&format!(" {arg}"));
+    }
+    line
+}
+
+/// Writes a single line of the command tree
+/// # Arguments
+/// * `f` - The writer to write the line to
+/// * `depth` - The depth of the command in the tree
+/// * `name` - The name of the command
+/// * `help` - The help string of the command
+pub(crate) fn write_info_line(
+    f: &mut dyn std::fmt::Write,
+    depth: usize,
+    name: &str,
+    help: &str,
+) -> std::fmt::Result {
+    let indent = "|  ".repeat(depth);
+    let string = format!("{}|-- {} ", indent, name);
+
+    writeln!(f, "{:.<35} {}", string, help)
+}
+
+/// Collects the paths of all commands and their subcommands as suggestion candidates
+/// # Arguments
+/// * `commands` - The commands to collect
+/// * `prefix` - The path leading to the commands
+/// * `candidates` - The collected pairs of paths and names
+pub(crate) fn collect_candidates<'a, T, E>(
+    commands: &HashMap<&'a str, Command<'a, T, E>>,
+    prefix: &str,
+    candidates: &mut Vec<(String, &'a str)>,
+) {
+    for (name, command) in commands {
+        let path = match prefix {
+            "" => name.to_string(),
+            prefix => format!("{prefix} {name}"),
+        };
+        collect_candidates(&command.subcommands, &path, candidates);
+        candidates.push((path, name));
+    }
+}
+
+/// Returns the commands of a command map sorted by their names
+/// # Arguments
+/// * `commands` - The commands to sort
+pub(crate) fn sorted_commands<'b, 'a, T, E>(
+    commands: &'b HashMap<&'a str, Command<'a, T, E>>,
+) -> Vec<&'b Command<'a, T, E>> {
+    let mut commands: Vec<&Command<'a, T, E>> = commands.values().collect();
+    commands.sort_by_key(|c| c.name);
+    commands
+}
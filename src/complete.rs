@@ -0,0 +1,404 @@
+//! Tab-completion of partially typed lines
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    cli::HELP_NAME, error::CommandError, lexer, parser::last_command, repl::EXIT_NAMES, Command,
+    OptSpec, CLI,
+};
+
+/// The candidates for completing the word at the cursor
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Completions {
+    /// The byte offset in the line where the word that gets completed starts
+    pub start: usize,
+    /// The candidates that can replace the line from `start` up to the cursor,
+    /// already escaped so that they form exactly one token
+    pub candidates: Vec<String>,
+}
+
+/// A shorthand for a function providing completion candidates from the state and the typed prefix
+pub type CompleteFn<T> = Box<dyn Fn(&T, &str) -> Vec<String> + Send + Sync>;
+
+/// Provides the candidates for completing the value of an argument or option
+pub enum ArgCompleter<T> {
+    /// A fixed set of values, e.g. the variants of an enum
+    Values(Vec<String>),
+    /// Paths on the file system
+    Paths,
+    /// A function that provides the candidates from the state and the typed prefix
+    Fn(CompleteFn<T>),
+}
+
+impl<T> ArgCompleter<T> {
+    /// Create a completer for a fixed set of values
+    /// # Arguments
+    /// * `values` - The values to complete
+    pub fn values(values: impl IntoIterator<Item = impl ToString>) -> Self {
+        Self::Values(values.into_iter().map(|v| v.to_string()).collect())
+    }
+
+    /// Create a completer that calls a function with the state and the typed prefix
+    /// # Arguments
+    /// * `f` - The function providing the candidates
+    pub fn from_fn(f: impl Fn(&T, &str) -> Vec<String> + Send + Sync + 'static) -> Self {
+        Self::Fn(Box::new(f))
+    }
+
+    /// Provide the candidates for a prefix, they still need to be filtered by the prefix
+    /// # Arguments
+    /// * `state` - The state of the CLI
+    /// * `prefix` - The part of the value that has already been typed
+    fn complete(&self, state: &T, prefix: &str) -> Vec<String> {
+        match self {
+            Self::Values(values) => values.clone(),
+            Self::Paths => complete_paths(prefix),
+            Self::Fn(f) => f(state, prefix),
+        }
+    }
+}
+
+impl<T: Send, E: CommandError> CLI<'_, T, E> {
+    /// Find the candidates for completing the word at the cursor. This completes
+    /// command and subcommand names, option names and the values of arguments
+    /// that have an [`ArgCompleter`] attached to them
+    /// # Arguments
+    /// * `line` - The line that is being typed
+    /// * `cursor` - The byte offset of the cursor in the line
+    pub fn complete(&self, line: &str, cursor: usize) -> Completions {
+        let mut cursor = cursor.min(line.len());
+        while !line.is_char_boundary(cursor) {
+            cursor -= 1;
+        }
+        let line = &line[..cursor];
+
+        // The last token is only completed if the cursor touches it,
+        // a command starts right after an operator
+        let mut tokens = lexer::tokenize_partial(line);
+        let (start, prefix) = match tokens.last() {
+            Some(token) if token.end == line.len() && token.operator.is_none() => {
+                let token = tokens.pop().expect("Expected last token to exist");
+                (token.start, token.value)
+            }
+            _ => (line.len(), String::new()),
+        };
+        let words: Vec<&str> = last_command(&tokens)
+            .iter()
+            .map(|t| t.value.as_str())
+            .collect();
+
+        // The path of a redirection follows its operator
+        let redirect = tokens
+            .last()
+            .is_some_and(|t| t.operator.is_some_and(|o| o.is_redirect()));
+        let mut candidates = if redirect {
+            complete_paths(&prefix)
+        } else {
+            self.complete_words(&words, &prefix)
+        };
+        candidates.retain(|c| c.starts_with(&prefix));
+        candidates.sort();
+        candidates.dedup();
+
+        Completions {
+            start,
+            candidates: candidates.iter().map(|c| lexer::escape(c)).collect(),
+        }
+    }
+
+    /// Find the candidates for the word following `words`
+    /// # Arguments
+    /// * `words` - The complete words before the cursor
+    /// * `prefix` - The part of the current word that has already been typed
+    fn complete_words(&self, words: &[&str], prefix: &str) -> Vec<String> {
+        let Some((first, rest)) = words.split_first() else {
+            let mut names = command_names(&self.commands);
+            if self.is_builtin_help(HELP_NAME) {
+                names.push(HELP_NAME.to_string());
+            }
+            for name in EXIT_NAMES.into_iter().filter(|n| self.is_builtin_exit(n)) {
+                names.push(name.to_string());
+            }
+            return names;
+        };
+
+        if let Some(command) = self.commands.get(first) {
+            command.complete(&self.state, rest, prefix)
+        } else if self.is_builtin_help(first) {
+            // The help command takes a path to a command
+            let mut commands = &self.commands;
+            for word in rest {
+                match commands.get(word) {
+                    Some(command) => commands = &command.subcommands,
+                    None => return Vec::new(),
+                }
+            }
+            command_names(commands)
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+impl<'a, T: Send, E: CommandError> Command<'a, T, E> {
+    /// Attach a completer to an argument or option of this command
+    /// # Arguments
+    /// * `name` - The name of the argument or option, as in its spec
+    /// * `completer` - The completer providing the candidates for its values
+    pub fn with_completer(mut self, name: &'a str, completer: ArgCompleter<T>) -> Self {
+        self.completers.insert(name, completer);
+        self
+    }
+
+    /// Find the candidates for the word following `words`
+    /// # Arguments
+    /// * `state` - The state of the CLI
+    /// * `words` - The complete words after the command name
+    /// * `prefix` - The part of the current word that has already been typed
+    fn complete(&self, state: &T, words: &[&str], prefix: &str) -> Vec<String> {
+        if let Some((first, rest)) = words.split_first() {
+            if let Some(subcommand) = self.subcommands.get(first) {
+                return subcommand.complete(state, rest, prefix);
+            }
+        }
+
+        let mut candidates = Vec::new();
+        if words.is_empty() {
+            candidates.extend(command_names(&self.subcommands));
+        }
+
+        // Find out which argument or option value is being typed
+        let mut position = 0;
+        let mut pending: Option<&OptSpec> = None;
+        let mut only_positional = false;
+        for word in words {
+            if pending.take().is_some() {
+                continue;
+            }
+            if !only_positional && *word == "--" {
+                only_positional = true;
+                continue;
+            }
+            if !only_positional {
+                if let Some((opt, inline)) = self.find_opt(word) {
+                    if !opt.flag && !inline {
+                        pending = Some(opt);
+                    }
+                    continue;
+                }
+            }
+            position += 1;
+        }
+
+        if pending.is_none() && !only_positional && prefix.starts_with('-') {
+            candidates.extend(self.opts.iter().flat_map(|o| o.names()));
+        }
+
+        let name = match pending {
+            Some(opt) => Some(opt.name),
+            None => self
+                .args
+                .get(position)
+                .or_else(|| self.args.last().filter(|a| a.variadic))
+                .map(|a| a.name),
+        };
+        if let Some(completer) = name.and_then(|name| self.completers.get(name)) {
+            candidates.extend(completer.complete(state, prefix));
+        }
+
+        candidates
+    }
+
+    /// Find the option a word refers to and whether the word contains its value
+    /// # Arguments
+    /// * `word` - The word to inspect
+    fn find_opt(&self, word: &str) -> Option<(&OptSpec<'a>, bool)> {
+        if let Some(long) = word.strip_prefix("--") {
+            let (name, inline) = match long.split_once('=') {
+                Some((name, _)) => (name, true),
+                None => (long, false),
+            };
+            let opt = self.opts.iter().find(|o| o.long == Some(name))?;
+            return Some((opt, inline));
+        }
+
+        let mut chars = word.strip_prefix('-')?.chars();
+        let short = chars.next()?;
+        let opt = self.opts.iter().find(|o| o.short == Some(short))?;
+        Some((opt, !chars.as_str().is_empty()))
+    }
+}
+
+/// The names of all commands in a command map
+/// # Arguments
+/// * `commands` - The commands to list
+fn command_names<T, E>(commands: &HashMap<&str, Command<'_, T, E>>) -> Vec<String> {
+    commands.keys().map(|name| name.to_string()).collect()
+}
+
+/// Lists the paths that start with `prefix`, directories end with a `/`
+/// # Arguments
+/// * `prefix` - The part of the path that has already been typed
+fn complete_paths(prefix: &str) -> Vec<String> {
+    let (dir, file) = match prefix.rfind('/') {
+        Some(i) => prefix.split_at(i + 1),
+        None => ("", prefix),
+    };
+    let read_dir = match dir {
+        "" => Path::new(".").read_dir(),
+        dir => Path::new(dir).read_dir(),
+    };
+    let Ok(entries) = read_dir else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(file) || (file.is_empty() && name.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+            Some(format!("{dir}{name}{}", if is_dir { "/" } else { "" }))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+    use crate::ArgSpec;
+
+    struct State {
+        users: Vec<String>,
+    }
+
+    fn noop(_: &mut State, _: Vec<String>) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn arg(name: &'static str, position: usize, variadic: bool) -> ArgSpec<'static> {
+        ArgSpec {
+            name,
+            ty: "String",
+            position,
+            help: None,
+            required: true,
+            variadic,
+            default: None,
+        }
+    }
+
+    fn cli() -> CLI<'static, State> {
+        let mut user = Command::new("user", "Manage users", noop);
+        user.add_subcommand(
+            Command::new("delete", "Delete users", noop)
+                .with_args(vec![arg("names", 0, true)])
+                .with_completer(
+                    "names",
+                    ArgCompleter::from_fn(|state: &State, _| state.users.clone()),
+                ),
+        );
+        user.add_subcommand(Command::new("add", "Add a user", noop));
+
+        let mut cli = CLI::new(State {
+            users: vec!["alice".to_string(), "bob".to_string(), "Mr X".to_string()],
+        });
+        cli.add_command(user);
+        cli.add_command(
+            Command::new("log", "Log something", noop)
+                .with_args(vec![arg("level", 0, false), arg("message", 1, false)])
+                .with_opts(vec![OptSpec {
+                    name: "format",
+                    ty: "String",
+                    short: Some('f'),
+                    long: Some("format"),
+                    help: None,
+                    flag: false,
+                }])
+                .with_completer("level", ArgCompleter::values(["debug", "info", "error"]))
+                .with_completer("format", ArgCompleter::values(["json", "text"])),
+        );
+        cli.add_help_command();
+        cli
+    }
+
+    fn candidates(line: &str) -> Vec<String> {
+        cli().complete(line, line.len()).candidates
+    }
+
+    #[test]
+    fn test_complete_commands() {
+        assert_eq!(candidates(""), vec!["exit", "help", "log", "quit", "user"]);
+        assert_eq!(candidates("u"), vec!["user"]);
+        assert_eq!(candidates("user "), vec!["add", "delete"]);
+        assert_eq!(candidates("user d"), vec!["delete"]);
+        assert!(candidates("nothing ").is_empty());
+        assert_eq!(candidates("log info hi && u"), vec!["user"]);
+        assert_eq!(candidates("nothing;user "), vec!["add", "delete"]);
+        assert_eq!(candidates("user > out; u"), vec!["user"]);
+        assert_eq!(candidates("user |"), candidates(""));
+        assert_eq!(candidates("log info hi &&"), candidates(""));
+        let completions = cli().complete("user;", 5);
+        assert_eq!(completions.start, 5);
+        assert!(completions.candidates.contains(&"log".to_string()));
+    }
+
+    #[test]
+    fn test_complete_help() {
+        assert_eq!(candidates("help "), vec!["log", "user"]);
+        assert_eq!(candidates("help user a"), vec!["add"]);
+    }
+
+    #[test]
+    fn test_complete_args() {
+        assert_eq!(candidates("log "), vec!["debug", "error", "info"]);
+        assert_eq!(candidates("log d"), vec!["debug"]);
+        assert!(candidates("log debug ").is_empty());
+        assert_eq!(candidates("log -f json e"), vec!["error"]);
+        assert_eq!(
+            candidates("user delete alice "),
+            vec!["Mr\\ X", "alice", "bob"]
+        );
+    }
+
+    #[test]
+    fn test_complete_opts() {
+        assert_eq!(candidates("log -"), vec!["--format", "-f"]);
+        assert_eq!(candidates("log --format "), vec!["json", "text"]);
+        assert_eq!(candidates("log -f t"), vec!["text"]);
+        assert!(candidates("log -- -").is_empty());
+    }
+
+    #[test]
+    fn test_complete_position() {
+        let cli = cli();
+
+        let completions = cli.complete("user del", 8);
+        assert_eq!(completions.start, 5);
+        assert_eq!(completions.candidates, vec!["delete"]);
+
+        let completions = cli.complete("user delete \"Mr", 15);
+        assert_eq!(completions.start, 12);
+        assert_eq!(completions.candidates, vec!["Mr\\ X"]);
+
+        let completions = cli.complete("log info", 2);
+        assert_eq!(completions.start, 0);
+        assert_eq!(completions.candidates, vec!["log"]);
+    }
+
+    #[test]
+    fn test_complete_paths() {
+        assert_eq!(candidates_for_paths("sr"), vec!["src/"]);
+        assert_eq!(candidates_for_paths("src/le"), vec!["src/lexer.rs"]);
+        assert_eq!(candidates("log >> sr"), vec!["src/"]);
+    }
+
+    fn candidates_for_paths(prefix: &str) -> Vec<String> {
+        let mut candidates = complete_paths(prefix);
+        candidates.retain(|c| c.starts_with(prefix));
+        candidates
+    }
+}
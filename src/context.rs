@@ -0,0 +1,193 @@
+//! The context a command is invoked in
+use std::{
+    collections::HashMap,
+    error::Error,
+    io::{self, Write},
+};
+
+use crate::{command::sorted_commands, ArgSpec, Command};
+
+/// The context a command is invoked in, it gives callbacks access to
+/// the state, the output and the variables of the CLI and describes the invocation.
+/// The context is a writer for the output of the CLI, so `writeln!(ctx, ...)` respects
+/// redirections like `dump > file`
+pub struct Context<'c, T, E = Box<dyn Error>> {
+    /// The state of the CLI
+    state: &'c mut T,
+    /// The sink receiving the output of the command
+    output: &'c mut (dyn Write + Send),
+    /// The raw line the command was invoked from
+    line: &'c str,
+    /// The path to the invoked command, e.g. `["tag", "add"]`
+    path: &'c [&'c str],
+    /// The arguments the invoked command expects
+    args: &'c [ArgSpec<'c>],
+    /// All the commands of the CLI
+    commands: &'c HashMap<&'c str, Command<'c, T, E>>,
+    /// The variables of the CLI
+    variables: &'c mut HashMap<String, String>,
+}
+
+impl<'c, T, E> Context<'c, T, E> {
+    /// Create a new context that does not describe a command yet
+    /// # Arguments
+    /// * `state` - The state of the CLI
+    /// * `output` - The sink receiving the output of the command
+    /// * `line` - The raw line the command is invoked from
+    /// * `commands` - All the commands of the CLI
+    /// * `variables` - The variables of the CLI
+    pub(crate) fn new(
+        state: &'c mut T,
+        output: &'c mut (dyn Write + Send),
+        line: &'c str,
+        commands: &'c HashMap<&'c str, Command<'c, T, E>>,
+        variables: &'c mut HashMap<String, String>,
+    ) -> Self {
+        Self {
+            state,
+            output,
+            line,
+            path: &[],
+            args: &[],
+            commands,
+            variables,
+        }
+    }
+
+    /// Describe the command this context is passed to
+    /// # Arguments
+    /// * `path` - The path to the invoked command
+    /// * `args` - The arguments the invoked command expects
+    pub(crate) fn with_command(mut self, path: &'c [&'c str], args: &'c [ArgSpec<'c>]) -> Self {
+        self.path = path;
+        self.args = args;
+        self
+    }
+
+    /// The state of the CLI
+    pub fn state(&self) -> &T {
+        self.state
+    }
+
+    /// The state of the CLI, for mutation
+    pub fn state_mut(&mut self) -> &mut T {
+        self.state
+    }
+
+    /// Take the state out of the context, e.g. to hand it to a callback without a context
+    pub(crate) fn into_state(self) -> &'c mut T {
+        self.state
+    }
+
+    /// The sink receiving the output of the command (see [`crate::CLI::set_output()`])
+    pub fn output(&mut self) -> &mut (dyn Write + Send) {
+        self.output
+    }
+
+    /// The raw line the command was invoked from
+    pub fn line(&self) -> &str {
+        self.line
+    }
+
+    /// The path to the invoked command as typed into the CLI, e.g. `["tag", "add"]`
+    pub fn path(&self) -> &[&str] {
+        self.path
+    }
+
+    /// The arguments the invoked command expects, ordered by their position
+    pub fn args(&self) -> &[ArgSpec<'c>] {
+        self.args
+    }
+
+    /// All the commands of the CLI, sorted by their names
+    pub fn commands(&self) -> Vec<&Command<'c, T, E>> {
+        sorted_commands(self.commands)
+    }
+
+    /// The value of a variable of the CLI (see [`crate::CLI::set_variable()`])
+    /// # Arguments
+    /// * `name` - The name of the variable
+    pub fn variable(&self, name: &str) -> Option<&str> {
+        self.variables.get(name).map(String::as_str)
+    }
+
+    /// Set a variable of the CLI, it outlives the invocation of the command
+    /// # Arguments
+    /// * `name` - The name of the variable
+    /// * `value` - The new value of the variable
+    /// # Returns
+    /// The previous value of the variable
+    pub fn set_variable(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Option<String> {
+        self.variables.insert(name.into(), value.into())
+    }
+
+    /// All the variables of the CLI
+    pub fn variables(&self) -> &HashMap<String, String> {
+        self.variables
+    }
+}
+
+impl<T, E> Write for Context<'_, T, E> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CLI;
+
+    /// Records the invocation in the variables
+    fn record(ctx: &mut Context<Vec<String>>, args: Vec<String>) -> Result<(), Box<dyn Error>> {
+        let names: Vec<&str> = ctx.commands().iter().map(|c| c.name()).collect();
+        let record = format!(
+            "{}|{}|{}",
+            ctx.line(),
+            ctx.path().join(" "),
+            names.join(",")
+        );
+        ctx.state_mut().push(record);
+
+        let count = ctx.variable("count").map_or(0, |c| c.parse().unwrap());
+        ctx.set_variable("count", (count + args.len()).to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_context() {
+        let mut tag = Command::new_context("tag", "Manage tags", record);
+        tag.add_subcommand(Command::new_context("add", "Add a tag", record));
+
+        let mut cli = CLI::new(Vec::new());
+        cli.add_command(tag);
+        cli.set_variable("count", "1");
+
+        cli.handle("tag add a b; tag c").unwrap();
+        cli.handle_args(["tag", "add", "d e"]);
+        assert_eq!(
+            cli.state(),
+            &[
+                "tag add a b; tag c|tag add|tag",
+                "tag add a b; tag c|tag|tag",
+                "tag add d\\ e|tag add|tag"
+            ]
+        );
+        assert_eq!(cli.variable("count"), Some("5"));
+        assert_eq!(cli.remove_variable("count"), Some("5".to_string()));
+
+        // Without a CLI, the context only describes the invocation
+        let command = Command::new_context("tag", "Manage tags", record);
+        let mut state = Vec::new();
+        command.handle(&mut state, &["x"]).unwrap();
+        assert_eq!(state, ["tag x|tag|"]);
+    }
+}
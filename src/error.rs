@@ -1,7 +1,194 @@
 //! Errors available from the `clik` crate
-use std::error::Error;
+use std::{
+    fmt::{Debug, Display},
+    io,
+};
+
+use crate::lexer::Operator;
+
+/// The error type of commands, this is `Box<dyn Error>` by default.
+/// The errors `clik` produces itself while looking up commands and parsing their
+/// arguments (see [`Error`]) and the errors of the output are converted into it,
+/// so every type that can be created from them, can be rendered and lives long
+/// enough is a command error
+pub trait CommandError: Debug + Display + From<Error> + From<io::Error> + 'static {}
+
+impl<E> CommandError for E where E: Debug + Display + From<Error> + From<io::Error> + 'static {}
 
 #[derive(Debug)]
+#[non_exhaustive]
+/// Describes an error that occurred while looking up a command, parsing its arguments
+/// or passing it the value of a pipeline.
+/// The command paths contain the names of the command and its parents as they have been typed
+pub enum Error {
+    /// There is no command with the typed name
+    UnknownCommand {
+        /// The name of the command as it has been typed
+        command: String,
+        /// Similar commands that might have been meant, the most similar one first
+        suggestions: Vec<String>,
+    },
+    /// The command exists and takes no arguments, but has no subcommand with the typed name.
+    /// Other commands receive such a word as an argument instead (see `Command::without_args()`)
+    UnknownSubcommand {
+        /// The path of the deepest command that exists
+        path: Vec<String>,
+        /// The name of the subcommand as it has been typed
+        subcommand: String,
+        /// The paths of similar commands that might have been meant, the most similar one first
+        suggestions: Vec<String>,
+    },
+    /// There is no argument at an expected position
+    MissingArgument {
+        /// The path of the command
+        path: Vec<String>,
+        /// The name of the argument
+        name: String,
+        /// The position the argument has been expected at. As the argument has not been
+        /// supplied, this counts the positional arguments only, ignoring any options
+        position: usize,
+        /// The type of the argument in string form
+        ty: String,
+    },
+    /// There is an argument, but it can't be parsed to the desired type
+    InvalidArgument {
+        /// The path of the command
+        path: Vec<String>,
+        /// The name of the argument
+        name: String,
+        /// The position of the argument
+        position: usize,
+        /// The type of the argument in string form
+        ty: String,
+        /// The inner error describing what exactly went wrong
+        inner: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// More arguments have been supplied than the command expects
+    TooManyArguments {
+        /// The path of the command
+        path: Vec<String>,
+        /// The position of the first unexpected argument
+        position: usize,
+        /// The unexpected arguments
+        arguments: Vec<String>,
+    },
+    /// An option has been supplied that the command does not know
+    UnknownOption {
+        /// The path of the command
+        path: Vec<String>,
+        /// The option as it has been supplied
+        option: String,
+        /// The position of the option
+        position: usize,
+    },
+    /// An option has been supplied more than once
+    DuplicateOption {
+        /// The path of the command
+        path: Vec<String>,
+        /// The name of the option
+        name: String,
+        /// The option as it has been supplied the second time
+        option: String,
+        /// The position of the second occurrence of the option
+        position: usize,
+    },
+    /// A value can't be passed to the command in a pipeline
+    Pipe {
+        /// The path of the command
+        path: Vec<String>,
+        /// The inner error describing what exactly went wrong
+        inner: PipeError,
+    },
+    /// An async command has been invoked through a synchronous handler
+    AsyncCommand {
+        /// The path of the command
+        path: Vec<String>,
+    },
+    /// The line can't be split up into its tokens
+    Lex(LexError),
+}
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownCommand {
+                command,
+                suggestions,
+            } => write_unknown(f, command, suggestions),
+            Self::UnknownSubcommand {
+                path,
+                subcommand,
+                suggestions,
+            } => write_unknown(f, &format!("{} {subcommand}", path.join(" ")), suggestions),
+            Self::MissingArgument {
+                path,
+                name,
+                position,
+                ty,
+            } => write!(
+                f,
+                "{}Argument '{name}' at position #{position} of type '{ty}' not found",
+                Prefix(path)
+            ),
+            Self::InvalidArgument {
+                path,
+                name,
+                position,
+                ty,
+                inner,
+            } => write!(
+                f,
+                "{}Failed to parse argument '{name}' at position #{position} of type '{ty}': {inner}",
+                Prefix(path)
+            ),
+            Self::TooManyArguments {
+                path,
+                position,
+                arguments,
+            } => write!(
+                f,
+                "{}Unexpected arguments '{}' starting at position #{position}",
+                Prefix(path),
+                arguments.join("', '")
+            ),
+            Self::UnknownOption {
+                path,
+                option,
+                position,
+            } => write!(
+                f,
+                "{}Unknown option '{option}' at position #{position}",
+                Prefix(path)
+            ),
+            Self::DuplicateOption {
+                path,
+                name,
+                option,
+                position,
+            } => write!(
+                f,
+                "{}Option '{name}' supplied again as '{option}' at position #{position}",
+                Prefix(path)
+            ),
+            Self::Pipe { path, inner } => write!(f, "{}{inner}", Prefix(path)),
+            Self::AsyncCommand { path } => write!(
+                f,
+                "{}Tried to use async callback with sync handler!",
+                Prefix(path)
+            ),
+            Self::Lex(e) => write!(f, "{e}"),
+        }
+    }
+}
+impl std::error::Error for Error {}
+
+impl From<LexError> for Error {
+    fn from(value: LexError) -> Self {
+        Self::Lex(value)
+    }
+}
+
+#[derive(Debug)]
+#[deprecated(since = "0.3.0", note = "use `Error::MissingArgument` instead")]
 /// Describes an error where there is no argument at an expected position
 pub struct MissingArgumentError {
     /// The name of the argument
@@ -11,6 +198,7 @@ pub struct MissingArgumentError {
     /// The type of the argument in string form
     pub ty: String,
 }
+#[allow(deprecated)]
 impl std::fmt::Display for MissingArgumentError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -20,9 +208,22 @@ impl std::fmt::Display for MissingArgumentError {
         )
     }
 }
+#[allow(deprecated)]
 impl std::error::Error for MissingArgumentError {}
+#[allow(deprecated)]
+impl From<MissingArgumentError> for Error {
+    fn from(value: MissingArgumentError) -> Self {
+        Self::MissingArgument {
+            path: Vec::new(),
+            name: value.name,
+            position: value.position,
+            ty: value.ty,
+        }
+    }
+}
 
 #[derive(Debug)]
+#[deprecated(since = "0.3.0", note = "use `Error::InvalidArgument` instead")]
 /// Describes an error where there is an argument, but it can't be parsed to the
 /// desired type
 pub struct WrongArgumentError {
@@ -33,8 +234,9 @@ pub struct WrongArgumentError {
     /// The type of the argument in string form
     pub ty: String,
     /// The inner error describing what exactly went wrong
-    pub inner: Box<dyn Error>,
+    pub inner: Box<dyn std::error::Error>,
 }
+#[allow(deprecated)]
 impl std::fmt::Display for WrongArgumentError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -44,4 +246,231 @@ impl std::fmt::Display for WrongArgumentError {
         )
     }
 }
+#[allow(deprecated)]
 impl std::error::Error for WrongArgumentError {}
+#[allow(deprecated)]
+impl From<WrongArgumentError> for Error {
+    /// The inner error is kept as its message, as it is not necessarily `Send + Sync`
+    fn from(value: WrongArgumentError) -> Self {
+        Self::InvalidArgument {
+            path: Vec::new(),
+            name: value.name,
+            position: value.position,
+            ty: value.ty,
+            inner: value.inner.to_string().into(),
+        }
+    }
+}
+
+/// Writes the message for a command that does not exist, including the suggestions
+/// # Arguments
+/// * `f` - The formatter to write to
+/// * `command` - The command path as it has been typed
+/// * `suggestions` - Similar commands that might have been meant
+fn write_unknown(
+    f: &mut std::fmt::Formatter<'_>,
+    command: &str,
+    suggestions: &[String],
+) -> std::fmt::Result {
+    let Some((last, rest)) = suggestions.split_last() else {
+        return write!(f, "No matching command has been found for '{command}'");
+    };
+
+    write!(f, "No matching command '{command}' — did you mean ")?;
+    if !rest.is_empty() {
+        write!(f, "'{}' or ", rest.join("', '"))?;
+    }
+    write!(f, "'{last}'?")
+}
+
+/// Renders a command path as the prefix of a message, e.g. `add: `
+struct Prefix<'p>(&'p [String]);
+impl std::fmt::Display for Prefix<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0.is_empty() {
+            true => Ok(()),
+            false => write!(f, "{}: ", self.0.join(" ")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Describes an error where a line can't be split up into its tokens
+pub enum LexError {
+    /// A quote has been opened, but never closed
+    UnterminatedQuote {
+        /// The quote character that has been opened
+        quote: char,
+        /// The byte offset of the opening quote
+        position: usize,
+    },
+    /// The line ends with an escape character that has nothing to escape
+    DanglingEscape {
+        /// The byte offset of the escape character
+        position: usize,
+    },
+    /// An operator is missing a command on one of its sides
+    MissingCommand {
+        /// The operator missing the command
+        operator: Operator,
+        /// The byte offset of the operator
+        position: usize,
+    },
+    /// A redirection is missing the path to the file
+    MissingPath {
+        /// The operator of the redirection
+        operator: Operator,
+        /// The byte offset of the operator
+        position: usize,
+    },
+}
+impl std::fmt::Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnterminatedQuote { quote, position } => {
+                write!(
+                    f,
+                    "Unterminated quote ({quote}) starting at byte {position}"
+                )
+            }
+            Self::DanglingEscape { position } => {
+                write!(f, "Nothing to escape after '\\' at byte {position}")
+            }
+            Self::MissingCommand { operator, position } => {
+                write!(f, "Missing command next to '{operator}' at byte {position}")
+            }
+            Self::MissingPath { operator, position } => {
+                write!(f, "Missing file after '{operator}' at byte {position}")
+            }
+        }
+    }
+}
+impl std::error::Error for LexError {}
+
+#[derive(Debug)]
+/// Describes an error that occurred while executing a line of a script
+pub struct ScriptLineError<E = Box<dyn std::error::Error>> {
+    /// The name of the script, e.g. its path
+    pub script: String,
+    /// The number of the line, starting at 1
+    pub line: usize,
+    /// The inner error describing what exactly went wrong
+    pub inner: E,
+}
+impl<E: Display> std::fmt::Display for ScriptLineError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.script, self.line, self.inner)
+    }
+}
+impl<E: Debug + Display> std::error::Error for ScriptLineError<E> {}
+
+#[derive(Debug)]
+/// Describes the errors that occurred while executing a script
+pub struct ScriptError<E = Box<dyn std::error::Error>> {
+    /// The errors of the failed lines, in the order they occurred
+    pub errors: Vec<ScriptLineError<E>>,
+}
+impl<E: Display> std::fmt::Display for ScriptError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, error) in self.errors.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{error}")?;
+        }
+        Ok(())
+    }
+}
+impl<E: Debug + Display> std::error::Error for ScriptError<E> {}
+
+#[derive(Debug)]
+/// Describes an error that occurred while executing a script file
+pub enum ScriptFileError<E = Box<dyn std::error::Error>> {
+    /// The script file can't be opened
+    Io(io::Error),
+    /// Lines of the script failed
+    Script(ScriptError<E>),
+}
+impl<E: Display> std::fmt::Display for ScriptFileError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::Script(e) => write!(f, "{e}"),
+        }
+    }
+}
+impl<E: Debug + Display> std::error::Error for ScriptFileError<E> {}
+
+impl<E> From<io::Error> for ScriptFileError<E> {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl<E> From<ScriptError<E>> for ScriptFileError<E> {
+    fn from(value: ScriptError<E>) -> Self {
+        Self::Script(value)
+    }
+}
+
+#[derive(Debug)]
+/// Describes an error where a value can't be passed between the commands of a pipeline
+pub enum PipeError {
+    /// A value has been piped into a command that does not accept one
+    InputNotAccepted,
+    /// The piped value is not of the type the command expects
+    WrongType {
+        /// The type the command expects in string form
+        expected: &'static str,
+    },
+}
+impl std::fmt::Display for PipeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InputNotAccepted => write!(f, "The command does not accept piped input"),
+            Self::WrongType { expected } => {
+                write!(f, "The piped value is not of the type '{expected}'")
+            }
+        }
+    }
+}
+impl std::error::Error for PipeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_send_sync() {
+        // Allows the errors to be wrapped by error types that have to be `Send`
+        assert_send_sync::<Error>();
+        assert_send_sync::<ScriptFileError<Error>>();
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_errors() {
+        let error = Error::from(MissingArgumentError {
+            name: "number".to_string(),
+            position: 0,
+            ty: "i32".to_string(),
+        });
+        assert_eq!(
+            error.to_string(),
+            "Argument 'number' at position #0 of type 'i32' not found"
+        );
+
+        let error = Error::from(WrongArgumentError {
+            name: "number".to_string(),
+            position: 1,
+            ty: "i32".to_string(),
+            inner: "invalid digit".into(),
+        });
+        assert!(matches!(
+            error,
+            Error::InvalidArgument { position: 1, inner, .. } if inner.to_string() == "invalid digit"
+        ));
+    }
+}
@@ -0,0 +1,343 @@
+//! Integration with the `rustyline` line editor
+use std::{borrow::Cow, error::Error, io};
+
+use rustyline::{
+    completion::Completer,
+    highlight::Highlighter,
+    hint::Hinter,
+    history::History,
+    validate::{ValidationContext, ValidationResult, Validator},
+    Context, Editor, Helper,
+};
+
+use crate::{
+    error::CommandError,
+    lexer,
+    parser::{last_command, parse_line},
+    LineSource, CLI,
+};
+
+/// The color for words that are known commands
+const KNOWN_COLOR: &str = "\x1b[32m";
+/// The color for words that should be commands, but are not
+const UNKNOWN_COLOR: &str = "\x1b[31m";
+/// The style for hints
+const HINT_STYLE: &str = "\x1b[2m";
+/// Resets all colors and styles
+const RESET: &str = "\x1b[0m";
+
+/// A `rustyline` helper that is driven by the command tree of a [`CLI`]:
+/// - Completes commands, options and arguments (see [`CLI::complete()`])
+/// - Hints the arguments that are still expected by the command being typed
+/// - Highlights known commands in green and unknown commands in red
+/// - Rejects lines with unterminated quotes or dangling escapes
+///
+/// The helper owns the CLI, so the interactive loop is run through [`ClikHelper::run()`]:
+/// ```ignore
+/// let mut editor = rustyline::Editor::new()?;
+/// editor.set_helper(Some(ClikHelper::new(cli)));
+/// ClikHelper::run(&mut editor)?;
+/// ```
+pub struct ClikHelper<'a, T: Send, E = Box<dyn Error>> {
+    cli: CLI<'a, T, E>,
+}
+
+impl<'a, T: Send, E: CommandError> ClikHelper<'a, T, E> {
+    /// Create a new helper for a CLI
+    /// # Arguments
+    /// * `cli` - The CLI to drive the helper
+    pub fn new(cli: CLI<'a, T, E>) -> Self {
+        Self { cli }
+    }
+
+    /// The CLI driving this helper
+    pub fn cli(&self) -> &CLI<'a, T, E> {
+        &self.cli
+    }
+
+    /// The CLI driving this helper, used for handling lines
+    pub fn cli_mut(&mut self) -> &mut CLI<'a, T, E> {
+        &mut self.cli
+    }
+
+    /// Take back the CLI driving this helper
+    pub fn into_cli(self) -> CLI<'a, T, E> {
+        self.cli
+    }
+
+    /// Run the interactive loop of the CLI owned by the helper of `editor`,
+    /// this behaves like [`CLI::run()`]
+    /// # Arguments
+    /// * `editor` - The editor to read the lines with
+    /// # Panics
+    /// If the editor has no helper set
+    pub fn run<I: History>(editor: &mut Editor<Self, I>) -> io::Result<()> {
+        editor
+            .helper_mut()
+            .expect("Expected editor to have a helper")
+            .cli
+            .run_pending_rc_file();
+
+        loop {
+            let prompt = editor
+                .helper()
+                .expect("Expected editor to have a helper")
+                .cli
+                .prompt();
+            let input = editor.read_line(&prompt)?;
+            let cli = &mut editor
+                .helper_mut()
+                .expect("Expected editor to have a helper")
+                .cli;
+            if cli.step(input).is_break() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl<T: Send, E: CommandError> Helper for ClikHelper<'_, T, E> {}
+
+impl<T: Send, E: CommandError> Completer for ClikHelper<'_, T, E> {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let completions = self.cli.complete(line, pos);
+        Ok((completions.start, completions.candidates))
+    }
+}
+
+impl<T: Send, E: CommandError> Hinter for ClikHelper<'_, T, E> {
+    type Hint = String;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+
+        let tokens = lexer::tokenize_partial(line);
+        let words: Vec<&str> = last_command(&tokens)
+            .iter()
+            .map(|t| t.value.as_str())
+            .collect();
+        let (command, depth) = self.cli.find_command(&words)?;
+
+        // Options shift the positions of the arguments, so the hint would be misleading
+        let typed = &words[depth..];
+        if typed.iter().any(|w| w.starts_with('-')) {
+            return None;
+        }
+        // While the command name itself is still being typed, the hint starts with a space
+        let separated = line.ends_with(char::is_whitespace);
+        if !separated && !typed.is_empty() {
+            return None;
+        }
+
+        let remaining: Vec<String> = command
+            .args()
+            .iter()
+            .skip(typed.len())
+            .map(|a| a.to_string())
+            .collect();
+        if remaining.is_empty() {
+            return None;
+        }
+
+        let hint = remaining.join(" ");
+        Some(if separated { hint } else { format!(" {hint}") })
+    }
+}
+
+impl<T: Send, E: CommandError> Highlighter for ClikHelper<'_, T, E> {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        let tokens = lexer::tokenize_partial(line);
+        if tokens.is_empty() {
+            return Cow::Borrowed(line);
+        }
+
+        // Highlight the command path of every command in a chain
+        let mut res = String::with_capacity(line.len());
+        let mut last = 0;
+        let mut operators = tokens.iter().filter_map(|t| t.operator);
+        let mut previous = None;
+        for command in tokens.split(|t| t.operator.is_some()) {
+            // The word after a redirect is the path of the file, not a command
+            let redirected = previous.is_some_and(|o: lexer::Operator| o.is_redirect());
+            previous = operators.next();
+            let Some(first) = command.first().filter(|_| !redirected) else {
+                continue;
+            };
+
+            let words: Vec<&str> = command.iter().map(|t| t.value.as_str()).collect();
+            let (color, end) = match self.cli.find_command(&words) {
+                Some((_, depth)) => (KNOWN_COLOR, command[depth - 1].end),
+                None if self.cli.is_builtin_help(&first.value)
+                    || self.cli.is_builtin_exit(&first.value) =>
+                {
+                    (KNOWN_COLOR, first.end)
+                }
+                None => (UNKNOWN_COLOR, first.end),
+            };
+
+            res.push_str(&line[last..first.start]);
+            res.push_str(&format!("{color}{}{RESET}", &line[first.start..end]));
+            last = end;
+        }
+        res.push_str(&line[last..]);
+
+        Cow::Owned(res)
+    }
+
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(format!("{HINT_STYLE}{hint}{RESET}"))
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        true
+    }
+}
+
+impl<T: Send, E: CommandError> Validator for ClikHelper<'_, T, E> {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        Ok(validate_line(ctx.input()))
+    }
+}
+
+/// Rejects lines that can't be split up into their tokens
+/// # Arguments
+/// * `line` - The line to validate
+fn validate_line(line: &str) -> ValidationResult {
+    match parse_line(line) {
+        Ok(_) => ValidationResult::Valid(None),
+        Err(e) => ValidationResult::Invalid(Some(format!(" ({e})"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustyline::history::MemHistory;
+
+    use super::*;
+    use crate::{ArgCompleter, ArgSpec, Command};
+
+    fn noop(_: &mut (), _: Vec<String>) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn arg(name: &'static str, position: usize) -> ArgSpec<'static> {
+        ArgSpec {
+            name,
+            ty: "i32",
+            position,
+            help: None,
+            required: true,
+            variadic: false,
+            default: None,
+        }
+    }
+
+    fn helper() -> ClikHelper<'static, ()> {
+        let mut tag = Command::new("tag", "Manage tags", noop);
+        tag.add_subcommand(
+            Command::new("add", "Add a tag", noop)
+                .with_args(vec![arg("id", 0), arg("tag", 1)])
+                .with_completer("tag", ArgCompleter::values(["red", "green"])),
+        );
+
+        let mut cli = CLI::new(());
+        cli.add_command(tag);
+        cli.add_help_command();
+        ClikHelper::new(cli)
+    }
+
+    #[test]
+    fn test_complete() {
+        let helper = helper();
+        let history = MemHistory::new();
+        let ctx = Context::new(&history);
+
+        let line = "tag add 1 r";
+        let (start, candidates) = helper.complete(line, line.len(), &ctx).unwrap();
+        assert_eq!(start, 10);
+        assert_eq!(candidates, vec!["red"]);
+    }
+
+    #[test]
+    fn test_hint() {
+        let helper = helper();
+        let history = MemHistory::new();
+        let ctx = Context::new(&history);
+        let hint = |line: &str| helper.hint(line, line.len(), &ctx);
+
+        assert_eq!(hint("tag add"), Some(" <id: i32> <tag: i32>".to_string()));
+        assert_eq!(hint("tag add "), Some("<id: i32> <tag: i32>".to_string()));
+        assert_eq!(hint("tag add 1 "), Some("<tag: i32>".to_string()));
+        assert_eq!(hint("tag add 1"), None);
+        assert_eq!(hint("tag add 1 2 "), None);
+        assert_eq!(hint("unknown "), None);
+        assert_eq!(
+            hint("tag add 1 2; tag add "),
+            Some("<id: i32> <tag: i32>".to_string())
+        );
+        assert_eq!(hint("tag add 1 2 && "), None);
+        assert_eq!(helper.hint("tag add", 3, &ctx), None);
+    }
+
+    #[test]
+    fn test_highlight() {
+        let helper = helper();
+
+        assert_eq!(
+            helper.highlight(" tag add 1", 0),
+            format!(" {KNOWN_COLOR}tag add{RESET} 1")
+        );
+        assert_eq!(
+            helper.highlight("help tag", 0),
+            format!("{KNOWN_COLOR}help{RESET} tag")
+        );
+        assert_eq!(
+            helper.highlight("quit", 0),
+            format!("{KNOWN_COLOR}quit{RESET}")
+        );
+        assert_eq!(
+            helper.highlight("tga add", 0),
+            format!("{UNKNOWN_COLOR}tga{RESET} add")
+        );
+        assert_eq!(helper.highlight("  ", 0), "  ");
+        assert_eq!(
+            helper.highlight("tag add 1 && tga;quit", 0),
+            format!(
+                "{KNOWN_COLOR}tag add{RESET} 1 && {UNKNOWN_COLOR}tga{RESET};{KNOWN_COLOR}quit{RESET}"
+            )
+        );
+        assert_eq!(
+            helper.highlight("tag add 1 > tag.txt; help >> help", 0),
+            format!("{KNOWN_COLOR}tag add{RESET} 1 > tag.txt; {KNOWN_COLOR}help{RESET} >> help")
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(matches!(
+            validate_line("tag add 1 \"red"),
+            ValidationResult::Invalid(Some(_))
+        ));
+        assert!(matches!(
+            validate_line("tag add 1 red\\"),
+            ValidationResult::Invalid(Some(_))
+        ));
+        assert!(matches!(
+            validate_line("tag add 1 red &&"),
+            ValidationResult::Invalid(Some(_))
+        ));
+        assert!(matches!(
+            validate_line("tag add 1 'red'"),
+            ValidationResult::Valid(None)
+        ));
+    }
+}
@@ -0,0 +1,416 @@
+//! The lexer that splits input lines into their tokens
+use std::{iter::Peekable, str::CharIndices};
+
+use crate::error::LexError;
+
+/// A single token of an input line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    /// The value of the token with all quotes and escapes resolved
+    pub value: String,
+    /// The byte offset of the first character of the token in the line
+    pub start: usize,
+    /// The byte offset past the last character of the token in the line
+    pub end: usize,
+    /// The operator if this token is an unquoted operator instead of a word
+    pub operator: Option<Operator>,
+}
+
+/// An operator that joins the commands of a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    /// `;` - Always run the following command
+    Sequence,
+    /// `&&` - Run the following command if the previous one succeeded
+    And,
+    /// `||` - Run the following command if the previous one failed
+    Or,
+    /// `|` - Pass the value of the previous command to the following one
+    Pipe,
+    /// `>` - Write the output of the commands to the following file, replacing its contents
+    Redirect,
+    /// `>>` - Append the output of the commands to the following file
+    Append,
+}
+
+impl Operator {
+    /// The operator as it is typed
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sequence => ";",
+            Self::And => "&&",
+            Self::Or => "||",
+            Self::Pipe => "|",
+            Self::Redirect => ">",
+            Self::Append => ">>",
+        }
+    }
+
+    /// Whether the operator redirects the output to the file following it
+    pub fn is_redirect(&self) -> bool {
+        matches!(self, Self::Redirect | Self::Append)
+    }
+}
+
+impl std::fmt::Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Splits a line into separate tokens according to the following rules:
+/// - Tokens are separated by any amount of whitespace
+/// - Text enclosed in single quotes (`'`) is taken literally
+/// - Text enclosed in double quotes (`"`) is taken literally, except for
+///   `\"` and `\\`, which produce `"` and `\` respectively
+/// - Outside of quotes, a backslash (`\`) escapes the following character
+/// - Quoted and unquoted parts that are not separated by whitespace form one token
+/// - The unquoted operators `;`, `&&`, `||`, `|`, `>` and `>>` form tokens of their own, even without
+///   surrounding whitespace (see [`Operator`])
+/// # Arguments
+/// * `line` - The line to tokenize
+pub fn tokenize(line: &str) -> Result<Vec<Token>, LexError> {
+    lex(line, false)
+}
+
+/// Splits a possibly incomplete line into separate tokens, following the rules of [`tokenize`].
+/// Unterminated quotes are closed and a dangling escape is dropped at the end of the line,
+/// which is useful for inspecting a line while it is being typed
+/// # Arguments
+/// * `line` - The line to tokenize
+pub fn tokenize_partial(line: &str) -> Vec<Token> {
+    lex(line, true).unwrap_or_default()
+}
+
+/// Escapes a value so that [`tokenize`] turns it into exactly one token with that value
+/// # Arguments
+/// * `value` - The value to escape
+pub fn escape(value: &str) -> String {
+    if value.is_empty() {
+        return "''".to_string();
+    }
+
+    let mut res = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c.is_whitespace() || matches!(c, '\'' | '"' | '\\' | ';' | '&' | '|' | '>') {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res
+}
+
+/// Splits a line into separate tokens
+/// # Arguments
+/// * `line` - The line to tokenize
+/// * `partial` - Whether to accept incomplete lines instead of failing
+fn lex(line: &str, partial: bool) -> Result<Vec<Token>, LexError> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    // The start offset and the value of the token that is currently being built
+    let mut current: Option<(usize, String)> = None;
+
+    while let Some((i, c)) = chars.next() {
+        if c.is_whitespace() {
+            if let Some((start, value)) = current.take() {
+                tokens.push(word(value, start, i));
+            }
+            continue;
+        }
+
+        if let Some(operator) = read_operator(&mut chars, c) {
+            if let Some((start, value)) = current.take() {
+                tokens.push(word(value, start, i));
+            }
+            let value = operator.as_str().to_string();
+            tokens.push(Token {
+                start: i,
+                end: i + value.len(),
+                value,
+                operator: Some(operator),
+            });
+            continue;
+        }
+
+        let (_, value) = current.get_or_insert_with(|| (i, String::new()));
+        let res = match c {
+            '\'' => read_single_quoted(&mut chars, value, i),
+            '"' => read_double_quoted(&mut chars, value, i),
+            '\\' => match chars.next() {
+                Some((_, escaped)) => {
+                    value.push(escaped);
+                    Ok(())
+                }
+                None => Err(LexError::DanglingEscape { position: i }),
+            },
+            c => {
+                value.push(c);
+                Ok(())
+            }
+        };
+
+        // All the remaining characters have been consumed in case of an error
+        if !partial {
+            res?;
+        }
+    }
+
+    if let Some((start, value)) = current {
+        tokens.push(word(value, start, line.len()));
+    }
+
+    Ok(tokens)
+}
+
+/// Creates a token that is a word
+/// # Arguments
+/// * `value` - The value of the word
+/// * `start` - The byte offset of the first character of the word
+/// * `end` - The byte offset past the last character of the word
+fn word(value: String, start: usize, end: usize) -> Token {
+    Token {
+        value,
+        start,
+        end,
+        operator: None,
+    }
+}
+
+/// Reads an operator starting with `c`, consuming its remaining characters
+/// # Arguments
+/// * `chars` - The characters following `c`
+/// * `c` - The current character
+fn read_operator(chars: &mut Peekable<CharIndices>, c: char) -> Option<Operator> {
+    let (single, double) = match c {
+        ';' => return Some(Operator::Sequence),
+        '&' => (None, Operator::And),
+        '|' => (Some(Operator::Pipe), Operator::Or),
+        '>' => (Some(Operator::Redirect), Operator::Append),
+        _ => return None,
+    };
+
+    // The remaining operators consist of the same character twice
+    match chars.peek() {
+        Some(&(_, next)) if next == c => {
+            chars.next();
+            Some(double)
+        }
+        _ => single,
+    }
+}
+
+/// Reads the contents of a single quoted string up to and including the closing quote
+/// # Arguments
+/// * `chars` - The characters following the opening quote
+/// * `value` - The value to append the contents to
+/// * `position` - The byte offset of the opening quote
+fn read_single_quoted(
+    chars: &mut Peekable<CharIndices>,
+    value: &mut String,
+    position: usize,
+) -> Result<(), LexError> {
+    for (_, c) in chars.by_ref() {
+        if c == '\'' {
+            return Ok(());
+        }
+        value.push(c);
+    }
+
+    Err(LexError::UnterminatedQuote {
+        quote: '\'',
+        position,
+    })
+}
+
+/// Reads the contents of a double quoted string up to and including the closing quote
+/// # Arguments
+/// * `chars` - The characters following the opening quote
+/// * `value` - The value to append the contents to
+/// * `position` - The byte offset of the opening quote
+fn read_double_quoted(
+    chars: &mut Peekable<CharIndices>,
+    value: &mut String,
+    position: usize,
+) -> Result<(), LexError> {
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '"' => return Ok(()),
+            '\\' => match chars.peek() {
+                Some(&(_, escaped @ ('"' | '\\'))) => {
+                    chars.next();
+                    value.push(escaped);
+                }
+                _ => value.push('\\'),
+            },
+            c => value.push(c),
+        }
+    }
+
+    Err(LexError::UnterminatedQuote {
+        quote: '"',
+        position,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tokenizes the line and returns only the values of the tokens
+    fn values(line: &str) -> Vec<String> {
+        tokenize(line)
+            .expect("Expected line to be valid")
+            .into_iter()
+            .map(|t| t.value)
+            .collect()
+    }
+
+    #[test]
+    fn test_no_split() {
+        let line = "help";
+        assert_eq!(values(line), vec!["help"]);
+    }
+
+    #[test]
+    fn test_split_spaces() {
+        let line = "help cmd";
+        assert_eq!(values(line), vec!["help", "cmd"])
+    }
+
+    #[test]
+    fn test_quotes_no_split() {
+        let line = "\"help\"";
+        assert_eq!(values(line), vec!["help"]);
+    }
+
+    #[test]
+    fn test_quotes_split_spaces() {
+        let line = "\"help\" \"cmd\"";
+        assert_eq!(values(line), vec!["help", "cmd"]);
+    }
+
+    #[test]
+    fn test_mixed_whitespace() {
+        let line = "  echo\t 5 \t\t6  ";
+        assert_eq!(values(line), vec!["echo", "5", "6"]);
+    }
+
+    #[test]
+    fn test_single_quotes() {
+        let line = r#"echo 'a "b" \c'"#;
+        assert_eq!(values(line), vec!["echo", r#"a "b" \c"#]);
+    }
+
+    #[test]
+    fn test_escapes() {
+        let line = r#"echo "say \"hi\" \\ \n" a\ b \'"#;
+        assert_eq!(values(line), vec!["echo", r#"say "hi" \ \n"#, "a b", "'"]);
+    }
+
+    #[test]
+    fn test_adjacent_parts() {
+        let line = r#"echo pre"fix"'ed'"#;
+        assert_eq!(values(line), vec!["echo", "prefixed"]);
+    }
+
+    #[test]
+    fn test_empty_quotes() {
+        let line = r#"echo "" ''"#;
+        assert_eq!(values(line), vec!["echo", "", ""]);
+    }
+
+    #[test]
+    fn test_utf8() {
+        let line = "echo \"größe\" ünïcödé";
+        assert_eq!(values(line), vec!["echo", "größe", "ünïcödé"]);
+    }
+
+    #[test]
+    fn test_offsets() {
+        let tokens = tokenize("ä \"bc\"  d").unwrap();
+        let offsets: Vec<(usize, usize)> = tokens.iter().map(|t| (t.start, t.end)).collect();
+        assert_eq!(offsets, vec![(0, 2), (3, 7), (9, 10)]);
+    }
+
+    #[test]
+    fn test_unterminated_quote() {
+        assert_eq!(
+            tokenize("echo \"größe"),
+            Err(LexError::UnterminatedQuote {
+                quote: '"',
+                position: 5
+            })
+        );
+        assert_eq!(
+            tokenize("ö 'a"),
+            Err(LexError::UnterminatedQuote {
+                quote: '\'',
+                position: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_partial() {
+        let tokens = tokenize_partial("echo \"a b");
+        assert_eq!(tokens[1].value, "a b");
+        assert_eq!((tokens[1].start, tokens[1].end), (5, 9));
+
+        let tokens = tokenize_partial("echo a\\");
+        assert_eq!(tokens[1].value, "a");
+        assert_eq!(tokens[1].end, 7);
+    }
+
+    #[test]
+    fn test_escape() {
+        for value in [
+            "",
+            "a b",
+            "größe",
+            r#"'it's' "q" \"#,
+            "tab\there",
+            "a;b&&c||d>e",
+        ] {
+            assert_eq!(
+                values(&format!("echo {}", escape(value))),
+                vec!["echo", value]
+            );
+        }
+    }
+
+    #[test]
+    fn test_operators() {
+        let tokens = tokenize("a;b && 'c;d' \\;|| e&f |g>h >>'>'").unwrap();
+        let tokens: Vec<(&str, Option<Operator>)> = tokens
+            .iter()
+            .map(|t| (t.value.as_str(), t.operator))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                ("a", None),
+                (";", Some(Operator::Sequence)),
+                ("b", None),
+                ("&&", Some(Operator::And)),
+                ("c;d", None),
+                (";", None),
+                ("||", Some(Operator::Or)),
+                ("e&f", None),
+                ("|", Some(Operator::Pipe)),
+                ("g", None),
+                (">", Some(Operator::Redirect)),
+                ("h", None),
+                (">>", Some(Operator::Append)),
+                (">", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dangling_escape() {
+        assert_eq!(
+            tokenize("echö \\"),
+            Err(LexError::DanglingEscape { position: 6 })
+        );
+    }
+}
@@ -1,7 +1,9 @@
 #![doc = include_str!("../README.md")]
 #![cfg_attr(nightly, feature(doc_cfg))]
 
-use std::{collections::HashMap, error::Error};
+use std::{collections::HashMap, error::Error, path::PathBuf, sync::Mutex};
+
+use error::CommandError;
 
 #[cfg_attr(nightly, doc(cfg(feature = "async")))]
 #[cfg(feature = "async")]
@@ -9,73 +11,217 @@ use std::{future::Future, pin::Pin};
 
 mod cli;
 mod command;
+mod complete;
+mod context;
 pub mod error;
+#[cfg_attr(nightly, doc(cfg(feature = "rustyline")))]
+#[cfg(feature = "rustyline")]
+mod helper;
+pub mod lexer;
+mod options;
+mod output;
+mod parser;
+mod pipe;
 mod prelude;
+mod repl;
+mod script;
+mod suggest;
+#[cfg(all(test, feature = "async"))]
+mod test_support;
 
 pub use clik_codegen::*;
+pub use complete::{ArgCompleter, CompleteFn, Completions};
+pub use context::Context;
+#[cfg_attr(nightly, doc(cfg(feature = "rustyline")))]
+#[cfg(feature = "rustyline")]
+pub use helper::ClikHelper;
+pub use options::{split_options, OptValue, SplitArgs};
+pub use output::Output;
+pub use pipe::{Pipe, Value};
+pub use repl::{Input, LineSource, PromptFn, RenderErrorFn};
+pub use script::ScriptMode;
 
 // NOTE: Taken from shellfish
 /// A shorthand for a synchronous function pointer
-pub type Fn<T> = fn(&mut T, Vec<String>) -> Result<(), Box<dyn Error>>;
+pub type Fn<T, E = Box<dyn Error>> = fn(&mut T, Vec<String>) -> Result<(), E>;
 
 // NOTE: Taken from shellfish
 /// A shorthand for an asynchronous function pointer
 #[cfg_attr(nightly, doc(cfg(feature = "async")))]
 #[cfg(feature = "async")]
-pub type AsyncFn<T> = fn(
-    &mut T,
-    Vec<String>,
-) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn Error>>> + Send + '_>>;
+pub type AsyncFn<T, E = Box<dyn Error>> =
+    fn(&mut T, Vec<String>) -> Pin<Box<dyn Future<Output = Result<(), E>> + Send + '_>>;
+
+/// A shorthand for a synchronous closure, it can capture values like configuration or handles
+pub type ClosureFn<T, E = Box<dyn Error>> =
+    Box<dyn FnMut(&mut T, Vec<String>) -> Result<(), E> + Send>;
+
+/// A shorthand for an asynchronous closure, it can capture values like configuration or handles
+#[cfg_attr(nightly, doc(cfg(feature = "async")))]
+#[cfg(feature = "async")]
+pub type AsyncClosureFn<T, E = Box<dyn Error>> = Box<
+    dyn for<'s> FnMut(
+            &'s mut T,
+            Vec<String>,
+        ) -> Pin<Box<dyn Future<Output = Result<(), E>> + Send + 's>>
+        + Send,
+>;
+
+/// A shorthand for a synchronous function pointer that receives the context of the invocation
+pub type ContextFn<T, E = Box<dyn Error>> =
+    fn(&mut Context<'_, T, E>, Vec<String>) -> Result<(), E>;
+
+/// A shorthand for an asynchronous function pointer that receives the context of the invocation
+#[cfg_attr(nightly, doc(cfg(feature = "async")))]
+#[cfg(feature = "async")]
+pub type AsyncContextFn<T, E = Box<dyn Error>> =
+    for<'c> fn(
+        &'c mut Context<'_, T, E>,
+        Vec<String>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), E>> + Send + 'c>>;
+
+/// A shorthand for a function pointer that receives the value piped from the previous
+/// command of a pipeline and produces the value for the next command
+pub type PipeFn<T, E = Box<dyn Error>> =
+    fn(&mut Context<'_, T, E>, Vec<String>, Option<Value>) -> Result<Option<Value>, E>;
 
 // NOTE: Partially taken from shellfish
 /// A function or callback can be either synchronous or asynchronous
-pub enum FnType<T> {
-    Sync(Fn<T>),
+pub enum FnType<T, E = Box<dyn Error>> {
+    Sync(Fn<T, E>),
     #[cfg_attr(nightly, doc(cfg(feature = "async")))]
     #[cfg(feature = "async")]
-    Async(AsyncFn<T>),
+    Async(AsyncFn<T, E>),
+    /// A synchronous closure, locked while it runs
+    Closure(Mutex<ClosureFn<T, E>>),
+    /// An asynchronous closure, locked while it creates its future
+    #[cfg_attr(nightly, doc(cfg(feature = "async")))]
+    #[cfg(feature = "async")]
+    AsyncClosure(Mutex<AsyncClosureFn<T, E>>),
+    /// A synchronous function receiving the context of the invocation
+    Context(ContextFn<T, E>),
+    /// An asynchronous function receiving the context of the invocation
+    #[cfg_attr(nightly, doc(cfg(feature = "async")))]
+    #[cfg(feature = "async")]
+    AsyncContext(AsyncContextFn<T, E>),
+    /// A synchronous function taking part in pipelines
+    Pipe(PipeFn<T, E>),
 }
 
-/// The `CLI` struct is the main handle for a CLI interface that holds all the commands
-pub struct CLI<'a, T: Send> {
+/// The `CLI` struct is the main handle for a CLI interface that holds all the commands.
+/// The commands of a CLI return errors of the type `E` (see [`CommandError`])
+pub struct CLI<'a, T: Send, E = Box<dyn Error>> {
     /// The state that gets represented to callbacks
     state: T,
     /// All the available commands
-    commands: HashMap<&'a str, Command<'a, T>>,
+    commands: HashMap<&'a str, Command<'a, T, E>>,
+    /// Whether the built-in `help` command is available
+    builtin_help: bool,
+    /// Renders the prompt of the interactive loop
+    prompt: PromptFn<T>,
+    /// Renders the errors of the interactive loop
+    render_error: RenderErrorFn<E>,
+    /// The startup file that still has to be executed by the interactive loop
+    rc_file: Option<PathBuf>,
+    /// The sink that receives the output of commands
+    output: Output,
+    /// The variables that are available to commands
+    variables: HashMap<String, String>,
 }
 
-impl<'a, T: Send> CLI<'_, T> {
-    /// Create a new CLI with an internal state
+impl<T: Send> CLI<'_, T> {
+    /// Create a new CLI with an internal state, its commands return boxed errors
     /// # Arguments
     /// * `state` - The state to provide to the callbacks
     pub fn new(state: T) -> Self {
+        Self::with_error_type(state)
+    }
+}
+
+impl<T: Send, E: CommandError> CLI<'_, T, E> {
+    /// Create a new CLI with an internal state whose commands return errors of the type `E`,
+    /// e.g. `CLI::<_, MyError>::with_error_type(state)`
+    /// # Arguments
+    /// * `state` - The state to provide to the callbacks
+    pub fn with_error_type(state: T) -> Self {
         Self {
             state,
             commands: HashMap::new(),
+            builtin_help: false,
+            prompt: Box::new(|_| ">> ".to_string()),
+            render_error: Box::new(|e| format!("ERROR: {e}")),
+            rc_file: None,
+            output: Box::new(std::io::stdout()),
+            variables: HashMap::new(),
         }
     }
 }
 
 /// A command that can have some subcommands
-pub struct Command<'a, T> {
+pub struct Command<'a, T, E = Box<dyn Error>> {
     name: &'a str,
     help: &'a str,
-    callback: FnType<T>,
-    subcommands: HashMap<&'a str, Command<'a, T>>,
+    callback: FnType<T, E>,
+    subcommands: HashMap<&'a str, Command<'a, T, E>>,
+    args: Vec<ArgSpec<'a>>,
+    opts: Vec<OptSpec<'a>>,
+    completers: HashMap<&'a str, ArgCompleter<T>>,
+    no_args: bool,
 }
 
-impl<'a, T: Send> Command<'a, T> {
+/// Describes an argument that a command expects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArgSpec<'a> {
+    /// The name of the argument
+    pub name: &'a str,
+    /// The type of the argument in string form
+    pub ty: &'a str,
+    /// The position of the argument
+    pub position: usize,
+    /// The description of the argument
+    pub help: Option<&'a str>,
+    /// Whether the argument has to be supplied, for variadic arguments
+    /// this means that at least one value has to be supplied
+    pub required: bool,
+    /// Whether the argument collects all remaining values
+    pub variadic: bool,
+    /// The value that is used if the argument is not supplied, in string form
+    pub default: Option<&'a str>,
+}
+
+/// Describes a named option that a command accepts, e.g. `-n 5` or `--verbose`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptSpec<'a> {
+    /// The name of the option
+    pub name: &'a str,
+    /// The type of the option value in string form
+    pub ty: &'a str,
+    /// The short name of the option, used as `-<short>`
+    pub short: Option<char>,
+    /// The long name of the option, used as `--<long>`
+    pub long: Option<&'a str>,
+    /// The description of the option
+    pub help: Option<&'a str>,
+    /// Whether the option is a flag that takes no value
+    pub flag: bool,
+}
+
+impl<'a, T: Send, E: CommandError> Command<'a, T, E> {
     /// Create a new command with a name and help string
     /// # Arguments
     /// * `name` - The name of the command, as typed into the CLI
     /// * `help` - The help string to describe this command
     /// * `callback` - The funcion to call when there is a match for this command
-    pub fn new(name: &'a str, help: &'a str, callback: Fn<T>) -> Self {
+    pub fn new(name: &'a str, help: &'a str, callback: Fn<T, E>) -> Self {
         Self {
             name,
             help,
             callback: FnType::Sync(callback),
             subcommands: HashMap::new(),
+            args: Vec::new(),
+            opts: Vec::new(),
+            completers: HashMap::new(),
+            no_args: false,
         }
     }
 
@@ -86,12 +232,128 @@ impl<'a, T: Send> Command<'a, T> {
     /// * `callback` - The async funcion to call when there is a match for this command
     #[cfg_attr(nightly, doc(cfg(feature = "async")))]
     #[cfg(feature = "async")]
-    pub fn new_async(name: &'a str, help: &'a str, callback: AsyncFn<T>) -> Self {
+    pub fn new_async(name: &'a str, help: &'a str, callback: AsyncFn<T, E>) -> Self {
         Self {
             name,
             help,
             callback: FnType::Async(callback),
             subcommands: HashMap::new(),
+            args: Vec::new(),
+            opts: Vec::new(),
+            completers: HashMap::new(),
+            no_args: false,
+        }
+    }
+
+    /// Create a new command with a name and help string from a closure,
+    /// which can capture values like configuration, handles or channels
+    /// # Arguments
+    /// * `name` - The name of the command, as typed into the CLI
+    /// * `help` - The help string to describe this command
+    /// * `callback` - The closure to call when there is a match for this command
+    pub fn from_closure(
+        name: &'a str,
+        help: &'a str,
+        callback: impl FnMut(&mut T, Vec<String>) -> Result<(), E> + Send + 'static,
+    ) -> Self {
+        Self {
+            name,
+            help,
+            callback: FnType::Closure(Mutex::new(Box::new(callback))),
+            subcommands: HashMap::new(),
+            args: Vec::new(),
+            opts: Vec::new(),
+            completers: HashMap::new(),
+            no_args: false,
+        }
+    }
+
+    /// Create a new command with a name and help string from an async closure,
+    /// which can capture values like configuration, handles or channels
+    /// # Arguments
+    /// * `name` - The name of the command, as typed into the CLI
+    /// * `help` - The help string to describe this command
+    /// * `callback` - The closure returning the boxed future to await when there is a match for this command
+    #[cfg_attr(nightly, doc(cfg(feature = "async")))]
+    #[cfg(feature = "async")]
+    pub fn from_async_closure(
+        name: &'a str,
+        help: &'a str,
+        callback: impl for<'s> FnMut(
+                &'s mut T,
+                Vec<String>,
+            ) -> Pin<Box<dyn Future<Output = Result<(), E>> + Send + 's>>
+            + Send
+            + 'static,
+    ) -> Self {
+        Self {
+            name,
+            help,
+            callback: FnType::AsyncClosure(Mutex::new(Box::new(callback))),
+            subcommands: HashMap::new(),
+            args: Vec::new(),
+            opts: Vec::new(),
+            completers: HashMap::new(),
+            no_args: false,
+        }
+    }
+
+    /// Create a new command with a name and help string whose callback receives
+    /// the context of the invocation
+    /// # Arguments
+    /// * `name` - The name of the command, as typed into the CLI
+    /// * `help` - The help string to describe this command
+    /// * `callback` - The funcion to call with the context when there is a match for this command
+    pub fn new_context(name: &'a str, help: &'a str, callback: ContextFn<T, E>) -> Self {
+        Self {
+            name,
+            help,
+            callback: FnType::Context(callback),
+            subcommands: HashMap::new(),
+            args: Vec::new(),
+            opts: Vec::new(),
+            completers: HashMap::new(),
+            no_args: false,
+        }
+    }
+
+    /// Create a new command with a name and help string whose async callback receives
+    /// the context of the invocation
+    /// # Arguments
+    /// * `name` - The name of the command, as typed into the CLI
+    /// * `help` - The help string to describe this command
+    /// * `callback` - The async funcion to call with the context when there is a match for this command
+    #[cfg_attr(nightly, doc(cfg(feature = "async")))]
+    #[cfg(feature = "async")]
+    pub fn new_async_context(name: &'a str, help: &'a str, callback: AsyncContextFn<T, E>) -> Self {
+        Self {
+            name,
+            help,
+            callback: FnType::AsyncContext(callback),
+            subcommands: HashMap::new(),
+            args: Vec::new(),
+            opts: Vec::new(),
+            completers: HashMap::new(),
+            no_args: false,
+        }
+    }
+
+    /// Create a new command with a name and help string that can take part in pipelines
+    /// # Arguments
+    /// * `name` - The name of the command, as typed into the CLI
+    /// * `help` - The help string to describe this command
+    /// * `callback` - The funcion to call with the piped value when there is a match for this command,
+    ///   the value it returns is piped into the next command
+    pub fn new_pipe(name: &'a str, help: &'a str, callback: PipeFn<T, E>) -> Self {
+        Self {
+            name,
+            help,
+            callback: FnType::Pipe(callback),
+            subcommands: HashMap::new(),
+            args: Vec::new(),
+            opts: Vec::new(),
+            completers: HashMap::new(),
+            no_args: false,
         }
     }
 }
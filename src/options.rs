@@ -0,0 +1,328 @@
+use crate::{
+    error::{CommandError, Error},
+    OptSpec,
+};
+
+/// The value of an option that has been supplied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptValue {
+    /// The position of the value, or of the option itself for flags
+    pub position: usize,
+    /// The value of the option, empty for flags
+    pub value: String,
+}
+
+/// Arguments that have been separated into positional arguments and options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitArgs {
+    /// The positional arguments in their original order
+    pub args: Vec<String>,
+    /// The positions of the positional arguments within the supplied arguments
+    pub positions: Vec<usize>,
+    /// The supplied options, in the same order as the option specs
+    pub options: Vec<Option<OptValue>>,
+}
+
+/// Separates the named options from the positional arguments. Options are supplied
+/// as `--<long>`, `--<long>=<value>`, `--<long> <value>`, `-<short>`, `-<short><value>`
+/// or `-<short> <value>`. Short flags can be combined like `-vd`, the first short option
+/// taking a value ends the group and takes the rest as its value, like `-vn5`.
+/// Everything after `--` is a positional argument.
+/// Negative numbers like `-5` are positional arguments as well.
+///
+/// This is used by the `clik_command` macro, the errors are converted into the error type of the command
+/// # Arguments
+/// * `args` - The arguments supplied to the command
+/// * `opts` - The options that the command accepts
+/// * `path` - The path of the command, used to describe errors
+pub fn split_options<E: CommandError>(
+    args: Vec<String>,
+    opts: &[OptSpec],
+    path: &[&str],
+) -> Result<SplitArgs, E> {
+    let mut positional = Vec::new();
+    let mut positions = Vec::new();
+    let mut options: Vec<Option<OptValue>> = vec![None; opts.len()];
+    let mut args = args.into_iter().enumerate();
+
+    while let Some((position, arg)) = args.next() {
+        if arg == "--" {
+            for (position, arg) in args.by_ref() {
+                positions.push(position);
+                positional.push(arg);
+            }
+            break;
+        }
+
+        let (index, option, inline) = if let Some(long) = arg.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+            (
+                opts.iter().position(|o| o.long == Some(name)),
+                arg.clone(),
+                value,
+            )
+        } else if let Some(mut short) = arg.strip_prefix('-').filter(|s| is_short_option(s)) {
+            // Flags can be combined like `-vd`, up to an option taking a value like `-vn5`
+            loop {
+                let mut chars = short.chars();
+                let name = chars.next();
+                let index = opts.iter().position(|o| o.short == name);
+                let rest = chars.as_str();
+                match index {
+                    Some(index) if opts[index].flag && !rest.is_empty() => {
+                        let flag = format!("-{}", &short[..short.len() - rest.len()]);
+                        check_duplicate(&options, opts, index, &flag, position, path)?;
+                        options[index] = Some(OptValue {
+                            position,
+                            value: String::new(),
+                        });
+                        short = rest;
+                    }
+                    _ => {
+                        let value = Some(rest).filter(|v| !v.is_empty());
+                        break (index, format!("-{short}"), value.map(str::to_string));
+                    }
+                }
+            }
+        } else {
+            positions.push(position);
+            positional.push(arg);
+            continue;
+        };
+
+        let Some(index) = index else {
+            return Err(Error::UnknownOption {
+                path: path.iter().map(ToString::to_string).collect(),
+                option,
+                position,
+            }
+            .into());
+        };
+        let opt = &opts[index];
+        check_duplicate(&options, opts, index, &option, position, path)?;
+
+        let value = match (opt.flag, inline) {
+            (true, None) => OptValue {
+                position,
+                value: String::new(),
+            },
+            (true, Some(_)) => {
+                return Err(Error::InvalidArgument {
+                    path: path.iter().map(ToString::to_string).collect(),
+                    name: opt.name.to_string(),
+                    position,
+                    ty: opt.ty.to_string(),
+                    inner: format!("Flag '{option}' does not take a value").into(),
+                }
+                .into())
+            }
+            (false, Some(value)) => OptValue { position, value },
+            (false, None) => match args.next() {
+                Some((position, value)) => OptValue { position, value },
+                None => {
+                    return Err(Error::MissingArgument {
+                        path: path.iter().map(ToString::to_string).collect(),
+                        name: opt.name.to_string(),
+                        position: position + 1,
+                        ty: opt.ty.to_string(),
+                    }
+                    .into())
+                }
+            },
+        };
+        options[index] = Some(value);
+    }
+
+    Ok(SplitArgs {
+        args: positional,
+        positions,
+        options,
+    })
+}
+
+/// Makes sure that an option has not been supplied before, every option can only be supplied once
+/// # Arguments
+/// * `options` - The options supplied so far, in the same order as the option specs
+/// * `opts` - The options that the command accepts
+/// * `index` - The index of the supplied option
+/// * `option` - The option as it has been supplied
+/// * `position` - The position of the option
+/// * `path` - The path of the command, used to describe errors
+fn check_duplicate(
+    options: &[Option<OptValue>],
+    opts: &[OptSpec],
+    index: usize,
+    option: &str,
+    position: usize,
+    path: &[&str],
+) -> Result<(), Error> {
+    match options[index] {
+        Some(_) => Err(Error::DuplicateOption {
+            path: path.iter().map(ToString::to_string).collect(),
+            name: opts[index].name.to_string(),
+            option: option.to_string(),
+            position,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Checks if the part after a single `-` denotes a short option rather than
+/// a positional argument like `-` or a negative number
+/// # Arguments
+/// * `short` - The argument without the leading `-`
+fn is_short_option(short: &str) -> bool {
+    match short.chars().next() {
+        Some(c) => !c.is_ascii_digit() && c != '.',
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTS: [OptSpec; 3] = [
+        OptSpec {
+            name: "count",
+            ty: "i32",
+            short: Some('n'),
+            long: Some("count"),
+            help: None,
+            flag: false,
+        },
+        OptSpec {
+            name: "verbose",
+            ty: "bool",
+            short: Some('v'),
+            long: Some("verbose"),
+            help: None,
+            flag: true,
+        },
+        OptSpec {
+            name: "dry_run",
+            ty: "bool",
+            short: Some('d'),
+            long: None,
+            help: None,
+            flag: true,
+        },
+    ];
+
+    fn split(args: &[&str]) -> Result<SplitArgs, Box<dyn std::error::Error>> {
+        split_options(
+            args.iter().map(|a| a.to_string()).collect(),
+            &OPTS,
+            &["log"],
+        )
+    }
+
+    fn value(position: usize, value: &str) -> Option<OptValue> {
+        Some(OptValue {
+            position,
+            value: value.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_no_options() {
+        let res = split(&["a", "-5", "-", "b"]).unwrap();
+        assert_eq!(res.args, vec!["a", "-5", "-", "b"]);
+        assert_eq!(res.positions, vec![0, 1, 2, 3]);
+        assert_eq!(res.options, vec![None, None, None]);
+    }
+
+    #[test]
+    fn test_long_options() {
+        let res = split(&["a", "--count", "5", "--verbose", "b"]).unwrap();
+        assert_eq!(res.args, vec!["a", "b"]);
+        assert_eq!(res.positions, vec![0, 4]);
+        assert_eq!(res.options, vec![value(2, "5"), value(3, ""), None]);
+
+        let res = split(&["--count=-5"]).unwrap();
+        assert_eq!(res.options, vec![value(0, "-5"), None, None]);
+    }
+
+    #[test]
+    fn test_short_options() {
+        let res = split(&["-n", "5", "a", "-v"]).unwrap();
+        assert_eq!(res.args, vec!["a"]);
+        assert_eq!(res.options, vec![value(1, "5"), value(3, ""), None]);
+
+        let res = split(&["-n5"]).unwrap();
+        assert_eq!(res.options, vec![value(0, "5"), None, None]);
+    }
+
+    #[test]
+    fn test_combined_flags() {
+        let res = split(&["-vd", "a"]).unwrap();
+        assert_eq!(res.args, vec!["a"]);
+        assert_eq!(res.options, vec![None, value(0, ""), value(0, "")]);
+
+        let res = split(&["-dvn5"]).unwrap();
+        assert_eq!(res.options, vec![value(0, "5"), value(0, ""), value(0, "")]);
+
+        let res = split(&["-vn", "5"]).unwrap();
+        assert_eq!(res.options, vec![value(1, "5"), value(0, ""), None]);
+
+        let err = split(&["-vx"]).expect_err("Expected unknown option");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::UnknownOption { option, position: 0, .. }) if option == "-x"
+        ));
+
+        let err = split(&["-d", "-vd"]).expect_err("Expected duplicate option");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::DuplicateOption { name, option, position: 1, .. })
+                if name == "dry_run" && option == "-d"
+        ));
+    }
+
+    #[test]
+    fn test_end_of_options() {
+        let res = split(&["-v", "--", "-n", "--verbose"]).unwrap();
+        assert_eq!(res.args, vec!["-n", "--verbose"]);
+        assert_eq!(res.positions, vec![2, 3]);
+        assert_eq!(res.options, vec![None, value(0, ""), None]);
+    }
+
+    #[test]
+    fn test_unknown_option() {
+        let err = split(&["a", "--size", "5"]).expect_err("Expected unknown option");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::UnknownOption { path, option, position: 1 })
+                if path == &["log"] && option == "--size"
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_option() {
+        let err = split(&["-n", "5", "--count=6"]).expect_err("Expected duplicate option");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::DuplicateOption { name, option, position: 2, .. })
+                if name == "count" && option == "--count=6"
+        ));
+    }
+
+    #[test]
+    fn test_missing_value() {
+        let err = split(&["--count"]).expect_err("Expected missing value");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::MissingArgument { path, name, position: 1, .. })
+                if path == &["log"] && name == "count"
+        ));
+
+        let err = split(&["--verbose=yes"]).expect_err("Expected flag error");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::InvalidArgument { path, name, .. }) if path == &["log"] && name == "verbose"
+        ));
+    }
+}
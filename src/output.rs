@@ -0,0 +1,163 @@
+//! The output of commands and its redirection to files
+use std::{
+    fs::OpenOptions,
+    io::{self, BufWriter, Write},
+    mem,
+};
+
+use crate::{error::CommandError, parser::Redirect, CLI};
+
+/// A shorthand for the sink that receives the output of commands
+pub type Output = Box<dyn Write + Send>;
+
+impl<T: Send, E: CommandError> CLI<'_, T, E> {
+    /// Set the sink that receives the output of commands, the default is `stdout`.
+    /// The output of a line is written to a file instead if it ends with `> file` or `>> file`
+    /// # Arguments
+    /// * `output` - The new sink
+    /// # Returns
+    /// The previous sink
+    pub fn set_output(&mut self, output: impl Write + Send + 'static) -> Output {
+        mem::replace(&mut self.output, Box::new(output))
+    }
+
+    /// The sink that receives the output of commands
+    pub fn output(&mut self) -> &mut (dyn Write + Send) {
+        self.output.as_mut()
+    }
+
+    /// Redirect the output to a file
+    /// # Arguments
+    /// * `redirect` - The redirection to apply, if there is one
+    /// # Returns
+    /// The previous sink that has to be restored by [`CLI::restore_output()`]
+    pub(crate) fn redirect_output(
+        &mut self,
+        redirect: Option<&Redirect>,
+    ) -> io::Result<Option<Output>> {
+        let Some(redirect) = redirect else {
+            return Ok(None);
+        };
+
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(redirect.append)
+            .truncate(!redirect.append)
+            .open(&redirect.path)?;
+        Ok(Some(mem::replace(
+            &mut self.output,
+            Box::new(BufWriter::new(file)),
+        )))
+    }
+
+    /// Restore the output after a redirection, flushing the output to the file
+    /// # Arguments
+    /// * `previous` - The sink returned by [`CLI::redirect_output()`]
+    pub(crate) fn restore_output(&mut self, previous: Option<Output>) -> io::Result<()> {
+        match previous {
+            Some(previous) => mem::replace(&mut self.output, previous).flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        error::Error,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::Command;
+
+    fn noop(_: &mut (), _: Vec<String>) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// A sink that can still be read after it has been handed to the CLI
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Shared {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    fn help_cli<'a>() -> CLI<'a, ()> {
+        let mut cli = CLI::new(());
+        cli.add_command(Command::new("echo", "Echo something", noop));
+        cli.add_help_command();
+        cli
+    }
+
+    #[test]
+    fn test_set_output() {
+        let mut cli = help_cli();
+        let shared = Shared::default();
+        cli.set_output(shared.clone());
+
+        cli.handle("help echo").unwrap();
+        assert_eq!(shared.contents(), cli.help(&["echo"]).unwrap());
+
+        write!(cli.output(), "done").unwrap();
+        assert!(shared.contents().ends_with("done"));
+    }
+
+    #[test]
+    fn test_redirect() {
+        let path = std::env::temp_dir().join(format!("clik-output-{}", std::process::id()));
+        let mut cli = help_cli();
+        let shared = Shared::default();
+        cli.set_output(shared.clone());
+
+        let help = cli.help(&["echo"]).unwrap();
+        cli.handle(&format!("help echo > '{}'", path.display()))
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), help);
+
+        cli.handle(&format!(
+            "help echo >> '{0}'; help echo >> '{0}'",
+            path.display()
+        ))
+        .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), help.repeat(3));
+
+        cli.handle(&format!("help echo > '{}' && help echo", path.display()))
+            .unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(contents, help);
+
+        // Only the line without a redirection reaches the original output
+        assert_eq!(shared.contents(), help);
+    }
+
+    #[test]
+    fn test_redirect_failed_pipeline() {
+        let path = std::env::temp_dir().join(format!("clik-failed-{}", std::process::id()));
+        let mut cli = help_cli();
+        let shared = Shared::default();
+        cli.set_output(shared.clone());
+
+        cli.handle(&format!("nothing | help echo > '{}'", path.display()))
+            .expect_err("Expected unknown command");
+        std::fs::remove_file(&path).unwrap();
+
+        // The failed pipeline must not keep the output redirected
+        cli.handle("help echo").unwrap();
+        assert_eq!(shared.contents(), cli.help(&["echo"]).unwrap());
+    }
+}
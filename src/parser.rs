@@ -0,0 +1,357 @@
+//! Parses lines into the pipelines of commands they consist of
+use std::mem;
+
+use crate::{
+    error::LexError,
+    lexer::{self, Operator, Token},
+};
+
+/// A pipeline of commands, the value of every command is passed to the next one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Pipeline {
+    /// The operator joining this pipeline to the previous one,
+    /// [`Operator::Sequence`] for the first pipeline
+    pub(crate) link: Operator,
+    /// The commands and their arguments
+    pub(crate) stages: Vec<Vec<String>>,
+    /// The file the output of the whole pipeline is redirected to
+    pub(crate) redirect: Option<Redirect>,
+}
+
+/// The redirection of the output of a pipeline to a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Redirect {
+    /// The path to the file
+    pub(crate) path: String,
+    /// Whether to append to the file instead of replacing its contents
+    pub(crate) append: bool,
+}
+
+impl Pipeline {
+    /// Create an empty pipeline
+    /// # Arguments
+    /// * `link` - The operator joining the pipeline to the previous one
+    fn new(link: Operator) -> Self {
+        Self {
+            link,
+            stages: Vec::new(),
+            redirect: None,
+        }
+    }
+
+    /// The commands and their arguments as string slices
+    pub(crate) fn prompts(&self) -> Vec<Vec<&str>> {
+        self.stages
+            .iter()
+            .map(|stage| stage.iter().map(String::as_str).collect())
+            .collect()
+    }
+}
+
+/// Splits a line into the pipelines that are joined by operators,
+/// an empty line contains no pipelines. A redirection can appear anywhere
+/// in a pipeline and applies to the whole pipeline, the last one wins
+/// # Arguments
+/// * `line` - The line to parse
+pub(crate) fn parse_line(line: &str) -> Result<Vec<Pipeline>, LexError> {
+    let mut pipelines = Vec::new();
+    let mut current = Pipeline::new(Operator::Sequence);
+    let mut words = Vec::new();
+    // The operator that still needs a command following it
+    let mut pending: Option<(Operator, usize)> = None;
+    // The redirection that still needs a path following it
+    let mut redirect: Option<(Operator, usize)> = None;
+    // The redirection of the current pipeline
+    let mut redirected: Option<(Operator, usize)> = None;
+
+    for token in lexer::tokenize(line)? {
+        if let Some((operator, position)) = redirect.take() {
+            if token.operator.is_some() {
+                return Err(LexError::MissingPath { operator, position });
+            }
+            current.redirect = Some(Redirect {
+                path: token.value,
+                append: operator == Operator::Append,
+            });
+            redirected = Some((operator, position));
+            continue;
+        }
+
+        let Some(operator) = token.operator else {
+            words.push(token.value);
+            pending = None;
+            continue;
+        };
+
+        if operator.is_redirect() {
+            redirect = Some((operator, token.start));
+            continue;
+        }
+
+        if words.is_empty() {
+            return Err(LexError::MissingCommand {
+                operator,
+                position: token.start,
+            });
+        }
+
+        current.stages.push(mem::take(&mut words));
+        if operator != Operator::Pipe {
+            pipelines.push(mem::replace(&mut current, Pipeline::new(operator)));
+            redirected = None;
+        }
+        pending = Some((operator, token.start));
+    }
+
+    if let Some((operator, position)) = redirect {
+        return Err(LexError::MissingPath { operator, position });
+    }
+
+    // Only a sequence may end the line, like in `a; b;`
+    match (pending, redirected) {
+        (Some((operator, position)), _) if operator != Operator::Sequence => {
+            return Err(LexError::MissingCommand { operator, position });
+        }
+        // A redirection without any command, like in `a; > file`
+        (_, Some((operator, position))) if words.is_empty() => {
+            return Err(LexError::MissingCommand { operator, position });
+        }
+        _ => {}
+    }
+
+    if !words.is_empty() {
+        current.stages.push(words);
+        pipelines.push(current);
+    }
+    Ok(pipelines)
+}
+
+/// The tokens of the last command in a line, following the last operator
+/// that separates commands. Redirections and their paths are left out
+/// # Arguments
+/// * `tokens` - The tokens of the line
+pub(crate) fn last_command(tokens: &[Token]) -> Vec<&Token> {
+    let start = tokens
+        .iter()
+        .rposition(|t| t.operator.is_some_and(|o| !o.is_redirect()))
+        .map_or(0, |i| i + 1);
+
+    let mut res = Vec::new();
+    let mut tokens = tokens[start..].iter();
+    while let Some(token) = tokens.next() {
+        match token.operator {
+            Some(_) => {
+                tokens.next();
+            }
+            None => res.push(token),
+        }
+    }
+    res
+}
+
+/// Tracks the results of a chain of pipelines to decide which ones run
+pub(crate) struct Chain<E> {
+    /// The result of the last pipeline that ran
+    status: Result<(), E>,
+    /// The first error that has not been handled by an `||`
+    error: Option<E>,
+}
+
+impl<E> Chain<E> {
+    /// Create the tracker for a new chain
+    pub(crate) fn new() -> Self {
+        Self {
+            status: Ok(()),
+            error: None,
+        }
+    }
+
+    /// Decides whether a pipeline runs, depending on the operator joining it
+    /// # Arguments
+    /// * `link` - The operator joining the pipeline to the previous one
+    pub(crate) fn should_run(&mut self, link: Operator) -> bool {
+        match link {
+            Operator::And => self.status.is_ok(),
+            // Running the alternative handles the error of the last pipeline
+            Operator::Or => self.status.is_err(),
+            // The other operators never join pipelines
+            Operator::Sequence | Operator::Pipe | Operator::Redirect | Operator::Append => {
+                self.settle();
+                true
+            }
+        }
+    }
+
+    /// Record the result of a pipeline that ran
+    /// # Arguments
+    /// * `result` - The result of the pipeline
+    pub(crate) fn record(&mut self, result: Result<(), E>) {
+        self.status = result;
+    }
+
+    /// The result of the whole chain: the first error that has not been handled by an `||`
+    pub(crate) fn finish(mut self) -> Result<(), E> {
+        self.settle();
+        self.error.map_or(Ok(()), Err)
+    }
+
+    /// Keep the error of the last pipeline, as it can't be handled anymore
+    fn settle(&mut self) {
+        if let Err(e) = mem::replace(&mut self.status, Ok(())) {
+            self.error.get_or_insert(e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+
+    /// Parses the line into pairs of links and stages, ignoring redirections
+    fn parse(line: &str) -> Vec<(Operator, Vec<Vec<String>>)> {
+        parse_line(line)
+            .expect("Expected line to be valid")
+            .into_iter()
+            .map(|p| (p.link, p.stages))
+            .collect()
+    }
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_chain() {
+        assert_eq!(
+            parse("connect prod && status; disconnect || echo 'a && b';"),
+            vec![
+                (Operator::Sequence, vec![words(&["connect", "prod"])]),
+                (Operator::And, vec![words(&["status"])]),
+                (Operator::Sequence, vec![words(&["disconnect"])]),
+                (Operator::Or, vec![words(&["echo", "a && b"])]),
+            ]
+        );
+        assert!(parse("  ").is_empty());
+    }
+
+    #[test]
+    fn test_parse_pipeline() {
+        assert_eq!(
+            parse("list users | filter active|count && echo '|'"),
+            vec![
+                (
+                    Operator::Sequence,
+                    vec![
+                        words(&["list", "users"]),
+                        words(&["filter", "active"]),
+                        words(&["count"])
+                    ]
+                ),
+                (Operator::And, vec![words(&["echo", "|"])]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_missing_command() {
+        for (line, operator, position) in [
+            ("&& a", Operator::And, 0),
+            ("a ; ; b", Operator::Sequence, 4),
+            ("a ||", Operator::Or, 2),
+            ("a && ;", Operator::Sequence, 5),
+            ("a | | b", Operator::Pipe, 4),
+            ("a |", Operator::Pipe, 2),
+            ("> out", Operator::Redirect, 0),
+            ("a; >> out", Operator::Append, 3),
+        ] {
+            assert_eq!(
+                parse_line(line),
+                Err(LexError::MissingCommand { operator, position }),
+                "{line}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_redirect() {
+        let pipelines = parse_line("dump config > backup.txt; a | b >>'log file' && c").unwrap();
+        let redirects: Vec<Option<Redirect>> = pipelines.into_iter().map(|p| p.redirect).collect();
+        assert_eq!(
+            redirects,
+            vec![
+                Some(Redirect {
+                    path: "backup.txt".to_string(),
+                    append: false
+                }),
+                Some(Redirect {
+                    path: "log file".to_string(),
+                    append: true
+                }),
+                None
+            ]
+        );
+
+        assert_eq!(
+            parse(">out dump  config"),
+            vec![(Operator::Sequence, vec![words(&["dump", "config"])])]
+        );
+        for (line, operator, position) in [
+            ("a >", Operator::Redirect, 2),
+            ("a > ; b", Operator::Redirect, 2),
+            ("a >> > b", Operator::Append, 2),
+        ] {
+            assert_eq!(
+                parse_line(line),
+                Err(LexError::MissingPath { operator, position }),
+                "{line}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_last_command() {
+        let tokens = lexer::tokenize("a | b > out c >> x").unwrap();
+        let words: Vec<&str> = last_command(&tokens)
+            .iter()
+            .map(|t| t.value.as_str())
+            .collect();
+        assert_eq!(words, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn test_chain() {
+        /// Runs a chain of results joined by the operators, returning the indices that ran
+        fn run(links: &[Operator], results: &[bool]) -> (Vec<usize>, Option<String>) {
+            let mut chain = Chain::<Box<dyn Error>>::new();
+            let mut ran = Vec::new();
+            for (i, (link, ok)) in links.iter().zip(results).enumerate() {
+                if chain.should_run(*link) {
+                    ran.push(i);
+                    chain.record(match ok {
+                        true => Ok(()),
+                        false => Err(format!("{i}").into()),
+                    });
+                }
+            }
+            (ran, chain.finish().err().map(|e| e.to_string()))
+        }
+
+        use Operator::*;
+        assert_eq!(
+            run(&[Sequence, And], &[false, true]),
+            (vec![0], Some("0".into()))
+        );
+        assert_eq!(run(&[Sequence, Or], &[false, true]), (vec![0, 1], None));
+        assert_eq!(run(&[Sequence, Or], &[true, true]), (vec![0], None));
+        assert_eq!(
+            run(&[Sequence, And, Or], &[false, true, true]),
+            (vec![0, 2], None)
+        );
+        assert_eq!(
+            run(&[Sequence, Sequence, Sequence], &[true, false, false]),
+            (vec![0, 1, 2], Some("1".into()))
+        );
+    }
+}
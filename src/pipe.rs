@@ -0,0 +1,81 @@
+//! Values passed between the commands of a pipeline
+use std::any::{type_name, Any};
+
+use crate::error::PipeError;
+
+/// A value that is passed from one command of a pipeline to the next one
+pub type Value = Box<dyn Any + Send>;
+
+/// The input of a command that has been piped from the previous command,
+/// like `filter` in `list users | filter active`. The input is empty if
+/// the command is not part of a pipeline or the previous command had no value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pipe<V> {
+    value: Option<V>,
+}
+
+impl<V: Any> Pipe<V> {
+    /// Create the input from the value of the previous command
+    /// # Arguments
+    /// * `value` - The value of the previous command, if there is one
+    pub fn from_value(value: Option<Value>) -> Result<Self, PipeError> {
+        let value = match value {
+            Some(value) => match value.downcast::<V>() {
+                Ok(value) => Some(*value),
+                Err(_) => {
+                    return Err(PipeError::WrongType {
+                        expected: type_name::<V>(),
+                    })
+                }
+            },
+            None => None,
+        };
+        Ok(Self { value })
+    }
+}
+
+impl<V> Pipe<V> {
+    /// Whether a value has been piped into the command
+    pub fn is_connected(&self) -> bool {
+        self.value.is_some()
+    }
+
+    /// The value that has been piped into the command
+    pub fn get(&self) -> Option<&V> {
+        self.value.as_ref()
+    }
+
+    /// Take the value that has been piped into the command
+    pub fn into_inner(self) -> Option<V> {
+        self.value
+    }
+}
+
+impl<V: IntoIterator> IntoIterator for Pipe<V> {
+    type Item = V::Item;
+    type IntoIter = std::iter::Flatten<std::option::IntoIter<V>>;
+
+    /// Iterate over the items of the piped value, nothing if there is none
+    fn into_iter(self) -> Self::IntoIter {
+        self.value.into_iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipe_from_value() {
+        let pipe = Pipe::<Vec<i32>>::from_value(Some(Box::new(vec![1, 2]))).unwrap();
+        assert!(pipe.is_connected());
+        assert_eq!(pipe.into_iter().collect::<Vec<_>>(), vec![1, 2]);
+
+        let pipe = Pipe::<Vec<i32>>::from_value(None).unwrap();
+        assert!(!pipe.is_connected());
+        assert_eq!(pipe.into_iter().count(), 0);
+
+        let err = Pipe::<i32>::from_value(Some(Box::new("text"))).expect_err("Expected wrong type");
+        assert!(matches!(err, PipeError::WrongType { expected: "i32" }));
+    }
+}
@@ -3,19 +3,23 @@
 /// as a function pointer.
 ///
 /// The first argument is a Type, which is the state. The second is the
-/// async function.
+/// async function. The error type of the function can be supplied
+/// between them, it defaults to `Box<dyn Error>`.
 #[cfg(feature = "async")]
 #[macro_export]
 #[cfg_attr(nightly, doc(cfg(feature = "async")))]
 macro_rules! async_fn {
-    ($state:ty, $inc:expr) => {{
+    ($state:ty, $error:ty, $inc:expr) => {{
        // I think the error message referred to here is spurious, but why take a chance?
        fn rustc_complains_if_this_name_conflicts_with_the_environment_even_though_its_probably_fine(
            state: &mut $state,
            args: Vec<String>
-       ) -> ::std::pin::Pin<Box<dyn ::std::future::Future<Output = Result<(), Box<dyn ::std::error::Error>>> + Send + '_ >> {
+       ) -> ::std::pin::Pin<Box<dyn ::std::future::Future<Output = Result<(), $error>> + Send + '_ >> {
             Box::pin($inc(state, args))
         }
         rustc_complains_if_this_name_conflicts_with_the_environment_even_though_its_probably_fine
-    }}
+    }};
+    ($state:ty, $inc:expr) => {
+        $crate::async_fn!($state, Box<dyn ::std::error::Error>, $inc)
+    }
 }
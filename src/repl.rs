@@ -0,0 +1,325 @@
+//! The interactive read-dispatch-report loop
+use std::{
+    error::Error,
+    io::{self, BufRead, IsTerminal, Write},
+    ops::ControlFlow,
+};
+
+use crate::{
+    error::{self, CommandError},
+    parser::{parse_line, Chain, Pipeline},
+    CLI,
+};
+
+/// The names of the built-in commands that leave the interactive loop
+pub(crate) const EXIT_NAMES: [&str; 2] = ["exit", "quit"];
+/// The description of the built-in commands that leave the interactive loop
+pub(crate) const EXIT_HELP: &str = "Leave the interactive loop";
+
+/// A shorthand for a function rendering the prompt from the state
+pub type PromptFn<T> = Box<dyn Fn(&T) -> String + Send + Sync>;
+
+/// A shorthand for a function rendering an error of a command for the user
+pub type RenderErrorFn<E = Box<dyn Error>> = Box<dyn Fn(&E) -> String + Send + Sync>;
+
+/// The result of reading a line from a [`LineSource`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    /// A complete line without the trailing line break
+    Line(String),
+    /// The user interrupted the input of the current line, e.g. by pressing `Ctrl-C`
+    Interrupted,
+    /// There are no more lines to read
+    Eof,
+}
+
+/// A source of input lines for [`CLI::run()`]
+pub trait LineSource {
+    /// Read the next line from this source
+    /// # Arguments
+    /// * `prompt` - The prompt to show to the user, if the source is interactive
+    fn read_line(&mut self, prompt: &str) -> io::Result<Input>;
+}
+
+impl<S: LineSource + ?Sized> LineSource for &mut S {
+    fn read_line(&mut self, prompt: &str) -> io::Result<Input> {
+        (**self).read_line(prompt)
+    }
+}
+
+/// The prompt is only shown if `stdin` is a terminal, so piped scripts produce no prompts
+impl LineSource for io::Stdin {
+    fn read_line(&mut self, prompt: &str) -> io::Result<Input> {
+        if self.is_terminal() {
+            let mut stdout = io::stdout();
+            write!(stdout, "{prompt}")?;
+            stdout.flush()?;
+        }
+
+        let mut line = String::new();
+        if self.lock().read_line(&mut line)? == 0 {
+            return Ok(Input::Eof);
+        }
+
+        let len = line.trim_end_matches(['\n', '\r']).len();
+        line.truncate(len);
+        Ok(Input::Line(line))
+    }
+}
+
+#[cfg_attr(nightly, doc(cfg(feature = "rustyline")))]
+#[cfg(feature = "rustyline")]
+impl<H: rustyline::Helper, I: rustyline::history::History> LineSource for rustyline::Editor<H, I> {
+    fn read_line(&mut self, prompt: &str) -> io::Result<Input> {
+        use rustyline::error::ReadlineError;
+
+        match self.readline(prompt) {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    self.add_history_entry(&line).map_err(io::Error::other)?;
+                }
+                Ok(Input::Line(line))
+            }
+            Err(ReadlineError::Interrupted) => Ok(Input::Interrupted),
+            Err(ReadlineError::Eof) => Ok(Input::Eof),
+            Err(ReadlineError::Io(e)) => Err(e),
+            Err(e) => Err(io::Error::other(e)),
+        }
+    }
+}
+
+impl<T: Send, E: CommandError> CLI<'_, T, E> {
+    /// Run the interactive loop: execute the startup file (see [`CLI::set_rc_file()`]),
+    /// then read a line from `source`, handle it and report errors through the
+    /// error renderer. Interrupting a line discards it, the loop returns
+    /// at the end of the input or once the built-in `exit` or `quit` command is invoked.
+    /// Commands named `exit` or `quit` that are added by the application take precedence
+    /// # Arguments
+    /// * `source` - The source to read the lines from
+    pub fn run(&mut self, mut source: impl LineSource) -> io::Result<()> {
+        self.run_pending_rc_file();
+        loop {
+            let prompt = self.prompt();
+            if self.step(source.read_line(&prompt)?).is_break() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Set a fixed prompt for the interactive loop, the default is `>> `
+    /// # Arguments
+    /// * `prompt` - The prompt to show
+    pub fn set_prompt(&mut self, prompt: impl Into<String>) {
+        let prompt = prompt.into();
+        self.prompt = Box::new(move |_| prompt.clone());
+    }
+
+    /// Set a function that renders the prompt for the interactive loop from the state,
+    /// e.g. to show parts of the state that change as commands get executed
+    /// # Arguments
+    /// * `prompt` - The function rendering the prompt
+    pub fn set_prompt_fn(&mut self, prompt: impl Fn(&T) -> String + Send + Sync + 'static) {
+        self.prompt = Box::new(prompt);
+    }
+
+    /// Set the function that renders the errors of the interactive loop,
+    /// the rendered errors are printed to `stderr`. The default renders `ERROR: <error>`
+    /// # Arguments
+    /// * `render` - The function rendering an error
+    pub fn set_error_renderer(&mut self, render: impl Fn(&E) -> String + Send + Sync + 'static) {
+        self.render_error = Box::new(render);
+    }
+
+    /// Render the prompt for the current state. This is used by [`CLI::run()`],
+    /// but can also be used to render the prompt for other line editors
+    pub fn prompt(&self) -> String {
+        (self.prompt)(&self.state)
+    }
+
+    /// Process one input of the interactive loop
+    /// # Arguments
+    /// * `input` - The input that has been read
+    /// # Returns
+    /// Whether the loop should stop
+    pub(crate) fn step(&mut self, input: Input) -> ControlFlow<()> {
+        let line = match input {
+            Input::Line(line) => line,
+            Input::Interrupted => return ControlFlow::Continue(()),
+            Input::Eof => return ControlFlow::Break(()),
+        };
+
+        let (result, flow) = self.handle_interactive(&line);
+        if let Err(e) = result {
+            eprintln!("{}", (self.render_error)(&e));
+        }
+        flow
+    }
+
+    /// Handle a line of the interactive loop like [`CLI::handle()`], but stop at
+    /// the first pipeline that invokes one of the built-in exit commands
+    /// # Arguments
+    /// * `line` - The line to handle
+    /// # Returns
+    /// The result of the commands that ran and whether the loop should stop
+    fn handle_interactive(&mut self, line: &str) -> (Result<(), E>, ControlFlow<()>) {
+        let pipelines = match parse_line(line) {
+            Ok(pipelines) => pipelines,
+            Err(e) => return (Err(error::Error::from(e).into()), ControlFlow::Continue(())),
+        };
+
+        let mut chain = Chain::new();
+        for pipeline in pipelines {
+            if !chain.should_run(pipeline.link) {
+                continue;
+            }
+            if self.is_exit(&pipeline) {
+                return (chain.finish(), ControlFlow::Break(()));
+            }
+            chain.record(self.run_pipeline(&pipeline, line));
+        }
+        (chain.finish(), ControlFlow::Continue(()))
+    }
+
+    /// Checks if a pipeline consists of one of the built-in exit commands only
+    /// # Arguments
+    /// * `pipeline` - The pipeline to check
+    fn is_exit(&self, pipeline: &Pipeline) -> bool {
+        match pipeline.prompts().as_slice() {
+            [prompt] => prompt
+                .first()
+                .is_some_and(|name| self.is_builtin_exit(name)),
+            _ => false,
+        }
+    }
+
+    /// Checks if `name` refers to one of the built-in commands leaving the interactive loop
+    /// # Arguments
+    /// * `name` - The name of the command
+    pub(crate) fn is_builtin_exit(&self, name: &str) -> bool {
+        EXIT_NAMES.contains(&name) && !self.commands.contains_key(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::Command;
+
+    fn increment(state: &mut i32, _: Vec<String>) -> Result<(), Box<dyn Error>> {
+        *state += 1;
+        Ok(())
+    }
+
+    /// A line source that replays prepared inputs and records the prompts
+    struct Replay {
+        inputs: VecDeque<Input>,
+        prompts: Vec<String>,
+    }
+
+    impl Replay {
+        fn new(lines: impl IntoIterator<Item = Input>) -> Self {
+            Self {
+                inputs: lines.into_iter().collect(),
+                prompts: Vec::new(),
+            }
+        }
+    }
+
+    impl LineSource for Replay {
+        fn read_line(&mut self, prompt: &str) -> io::Result<Input> {
+            self.prompts.push(prompt.to_string());
+            Ok(self.inputs.pop_front().unwrap_or(Input::Eof))
+        }
+    }
+
+    fn line(line: &str) -> Input {
+        Input::Line(line.to_string())
+    }
+
+    fn counter_cli<'a>() -> CLI<'a, i32> {
+        let mut cli = CLI::new(0);
+        cli.add_command(Command::new("inc", "Increment the counter", increment));
+        cli
+    }
+
+    #[test]
+    fn test_run_until_eof() {
+        let mut cli = counter_cli();
+        let mut source = Replay::new([line("inc"), Input::Interrupted, line(""), line("inc")]);
+
+        cli.run(&mut source).unwrap();
+        assert_eq!(cli.state, 2);
+        assert_eq!(source.prompts.len(), 5);
+        assert!(source.prompts.iter().all(|p| p == ">> "));
+    }
+
+    #[test]
+    fn test_run_exit() {
+        for exit in [
+            "exit",
+            "quit",
+            "  quit now",
+            "inc && exit",
+            "inc; quit",
+            "fail || exit",
+        ] {
+            let mut cli = counter_cli();
+            cli.run(Replay::new([line("inc"), line(exit), line("inc")]))
+                .unwrap();
+            let expected = if exit.starts_with("inc") { 2 } else { 1 };
+            assert_eq!(cli.state, expected, "Unexpected state after '{exit}'");
+        }
+
+        // Exiting depends on the previous commands like any other command
+        let mut cli = counter_cli();
+        cli.run(Replay::new([line("fail && exit"), line("inc")]))
+            .unwrap();
+        assert_eq!(cli.state, 1);
+
+        // Commands of the application take precedence
+        let mut cli = counter_cli();
+        cli.add_command(Command::new("exit", "Increment the counter", increment));
+        cli.run(Replay::new([line("exit"), line("inc")])).unwrap();
+        assert_eq!(cli.state, 2);
+    }
+
+    #[test]
+    fn test_run_continues_after_errors() {
+        let mut cli = counter_cli();
+        cli.set_error_renderer(|e| format!("custom: {e}"));
+        cli.run(Replay::new([line("nothing"), line("inc \"a"), line("inc")]))
+            .unwrap();
+        assert_eq!(cli.state, 1);
+    }
+
+    #[test]
+    fn test_run_prompt() {
+        let mut cli = counter_cli();
+        cli.set_prompt("count> ");
+        let mut source = Replay::new([]);
+        cli.run(&mut source).unwrap();
+        assert_eq!(source.prompts, vec!["count> "]);
+
+        cli.set_prompt_fn(|count| format!("[{count}]> "));
+        let mut source = Replay::new([line("inc"), line("inc")]);
+        cli.run(&mut source).unwrap();
+        assert_eq!(source.prompts, vec!["[0]> ", "[1]> ", "[2]> "]);
+    }
+
+    #[test]
+    fn test_prompt() {
+        fn count_prompt(count: &i32) -> String {
+            format!("{count} pending> ")
+        }
+
+        let mut cli = counter_cli();
+        assert_eq!(cli.prompt(), ">> ");
+
+        cli.set_prompt_fn(count_prompt);
+        assert_eq!(cli.prompt(), "0 pending> ");
+        cli.handle("inc").unwrap();
+        assert_eq!(cli.prompt(), "1 pending> ");
+    }
+}
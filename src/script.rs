@@ -0,0 +1,288 @@
+//! Non-interactive execution of scripts
+use std::{
+    ffi::OsString,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::{CommandError, ScriptError, ScriptFileError, ScriptLineError},
+    CLI,
+};
+
+/// The name used for scripts that are not read from a file
+const READER_NAME: &str = "<input>";
+
+/// Decides how a script continues once one of its lines failed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScriptMode {
+    /// Stop at the first failing line
+    #[default]
+    StopOnError,
+    /// Execute all lines and collect the errors of the failing ones
+    Continue,
+}
+
+impl<T: Send, E: CommandError> CLI<'_, T, E> {
+    /// Execute a script file line by line, see [`CLI::run_reader()`] for the rules
+    /// # Arguments
+    /// * `path` - The path to the script
+    /// * `mode` - How to continue once a line failed
+    /// # Returns
+    /// [`ScriptFileError::Io`] if the file can't be opened, [`ScriptFileError::Script`] if lines failed
+    pub fn run_script(
+        &mut self,
+        path: impl AsRef<Path>,
+        mode: ScriptMode,
+    ) -> Result<(), ScriptFileError<E>> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        self.run_lines(BufReader::new(file), &path.display().to_string(), mode)?;
+        Ok(())
+    }
+
+    /// Execute all lines from a reader, e.g. `stdin`. Every line is handled by [`CLI::handle()`],
+    /// except for blank lines and lines starting with `#`, which are skipped
+    /// # Arguments
+    /// * `reader` - The reader to read the lines from
+    /// * `mode` - How to continue once a line failed
+    pub fn run_reader(
+        &mut self,
+        reader: impl BufRead,
+        mode: ScriptMode,
+    ) -> Result<(), ScriptError<E>> {
+        self.run_lines(reader, READER_NAME, mode)
+    }
+
+    /// Set the startup file that [`CLI::run()`] executes before the first prompt,
+    /// see [`CLI::run_rc_file()`]
+    /// # Arguments
+    /// * `path` - The path to the startup file, e.g. `~/.toolrc`
+    pub fn set_rc_file(&mut self, path: impl Into<PathBuf>) {
+        self.rc_file = Some(path.into());
+    }
+
+    /// Execute a startup file, e.g. to define variables or to prepare the state.
+    /// A missing file is skipped, all other errors are rendered through the
+    /// error renderer (see [`CLI::set_error_renderer()`]) and printed to `stderr`,
+    /// including the file and line, without stopping at the first failing line
+    /// # Arguments
+    /// * `path` - The path to the startup file, a leading `~` is replaced by the home directory
+    pub fn run_rc_file(&mut self, path: impl AsRef<Path>) {
+        let path = expand_home(path.as_ref(), std::env::var_os("HOME"));
+        let path = path.as_path();
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return,
+            Err(e) => {
+                let e = io::Error::new(e.kind(), format!("{}: {e}", path.display()));
+                eprintln!("{}", (self.render_error)(&e.into()));
+                return;
+            }
+        };
+
+        let script = path.display().to_string();
+        if let Err(e) = self.run_lines(BufReader::new(file), &script, ScriptMode::Continue) {
+            for e in e.errors {
+                let rendered = (self.render_error)(&e.inner);
+                eprintln!("{}:{}: {rendered}", e.script, e.line);
+            }
+        }
+    }
+
+    /// Execute the startup file set by [`CLI::set_rc_file()`], if it hasn't been executed yet
+    pub(crate) fn run_pending_rc_file(&mut self) {
+        if let Some(path) = self.rc_file.take() {
+            self.run_rc_file(path);
+        }
+    }
+
+    /// Execute all lines from a reader
+    /// # Arguments
+    /// * `reader` - The reader to read the lines from
+    /// * `script` - The name of the script for the errors
+    /// * `mode` - How to continue once a line failed
+    pub(crate) fn run_lines(
+        &mut self,
+        reader: impl BufRead,
+        script: &str,
+        mode: ScriptMode,
+    ) -> Result<(), ScriptError<E>> {
+        let mut errors = Vec::new();
+
+        for (i, line) in reader.lines().enumerate() {
+            let error = |inner| ScriptLineError {
+                script: script.to_string(),
+                line: i + 1,
+                inner,
+            };
+
+            // A line that can't be read means that the rest can't be trusted either
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    errors.push(error(e.into()));
+                    break;
+                }
+            };
+
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            if let Err(e) = self.handle(&line) {
+                errors.push(error(e));
+                if mode == ScriptMode::StopOnError {
+                    break;
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ScriptError { errors })
+        }
+    }
+}
+
+/// Replaces a leading `~` of a path with the home directory, like a shell does.
+/// The path is kept as it is if the home directory is unknown
+/// # Arguments
+/// * `path` - The path to expand
+/// * `home` - The home directory, usually `$HOME`
+fn expand_home(path: &Path, home: Option<OsString>) -> PathBuf {
+    match (path.strip_prefix("~"), home) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, io::Cursor};
+
+    use super::*;
+    use crate::{error, Command, Input, LineSource};
+
+    fn add(state: &mut i32, args: Vec<String>) -> Result<(), Box<dyn Error>> {
+        *state += args[0].parse::<i32>()?;
+        Ok(())
+    }
+
+    const SCRIPT: &str =
+        "# Add some numbers\nadd 1\n\n  add x\nnothing\n   # indented comment\nadd 2\n";
+
+    fn sum_cli<'a>() -> CLI<'a, i32> {
+        let mut cli = CLI::new(0);
+        cli.add_command(Command::new("add", "Add a number", add));
+        cli
+    }
+
+    #[test]
+    fn test_run_reader_stop() {
+        let mut cli = sum_cli();
+        let err = cli
+            .run_reader(Cursor::new(SCRIPT), ScriptMode::StopOnError)
+            .expect_err("Expected script to fail");
+
+        assert_eq!(cli.state, 1);
+        assert_eq!(err.errors.len(), 1);
+        assert_eq!(err.errors[0].line, 4);
+        assert!(err.to_string().starts_with("<input>:4: "));
+    }
+
+    #[test]
+    fn test_run_reader_continue() {
+        let mut cli = sum_cli();
+        let err = cli
+            .run_reader(Cursor::new(SCRIPT), ScriptMode::Continue)
+            .expect_err("Expected script to fail");
+
+        assert_eq!(cli.state, 3);
+        let lines: Vec<usize> = err.errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![4, 5]);
+        assert!(matches!(
+            err.errors[1].inner.downcast_ref::<error::Error>(),
+            Some(error::Error::UnknownCommand { .. })
+        ));
+    }
+
+    #[test]
+    fn test_run_script() {
+        let path = std::env::temp_dir().join(format!("clik-script-{}", std::process::id()));
+        std::fs::write(&path, "add 5\nadd x\n").unwrap();
+
+        let mut cli = sum_cli();
+        let err = cli
+            .run_script(&path, ScriptMode::Continue)
+            .expect_err("Expected script to fail");
+        std::fs::remove_file(&path).unwrap();
+
+        let ScriptFileError::Script(err) = err else {
+            panic!("Expected failing lines, got {err:?}");
+        };
+        assert!(err
+            .to_string()
+            .starts_with(&format!("{}:2: ", path.display())));
+        assert_eq!(cli.state, 5);
+
+        let err = cli
+            .run_script(&path, ScriptMode::Continue)
+            .expect_err("Expected missing script to fail");
+        assert!(matches!(err, ScriptFileError::Io(e) if e.kind() == io::ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn test_rc_file() {
+        let path = std::env::temp_dir().join(format!("clik-rc-{}", std::process::id()));
+        std::fs::write(
+            &path,
+            "add 5
+add x
+add 2
+",
+        )
+        .unwrap();
+
+        let mut cli = sum_cli();
+        cli.run_rc_file(&path);
+        assert_eq!(cli.state, 7);
+
+        // The startup file runs before the first prompt, but only once
+        struct Empty;
+        impl LineSource for Empty {
+            fn read_line(&mut self, _: &str) -> io::Result<Input> {
+                Ok(Input::Eof)
+            }
+        }
+
+        cli.set_rc_file(&path);
+        cli.run(Empty).unwrap();
+        assert_eq!(cli.state, 14);
+        cli.run(Empty).unwrap();
+        assert_eq!(cli.state, 14);
+        std::fs::remove_file(&path).unwrap();
+
+        // Missing files are skipped
+        cli.run_rc_file(&path);
+        assert_eq!(cli.state, 14);
+    }
+
+    #[test]
+    fn test_expand_home() {
+        let home = Some(OsString::from("/home/user"));
+        let expand = |path: &str| expand_home(Path::new(path), home.clone());
+
+        assert_eq!(expand("~/.toolrc"), Path::new("/home/user/.toolrc"));
+        assert_eq!(expand("~"), Path::new("/home/user"));
+        assert_eq!(expand("~other/.toolrc"), Path::new("~other/.toolrc"));
+        assert_eq!(expand("/etc/~/.toolrc"), Path::new("/etc/~/.toolrc"));
+        assert_eq!(
+            expand_home(Path::new("~/.toolrc"), None),
+            Path::new("~/.toolrc")
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! Suggestions for mistyped command names
+
+/// The maximum amount of suggestions that are made
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Finds the candidates that are close to the typed word, ordered by their similarity.
+/// A candidate is a pair of the suggested text and the name to compare the word to,
+/// e.g. `("tag add", "add")` suggests `tag add` if the word is close to `add`
+/// # Arguments
+/// * `word` - The word that has been typed
+/// * `candidates` - The candidates to suggest from
+pub fn suggest<'b>(
+    word: &str,
+    candidates: impl IntoIterator<Item = (String, &'b str)>,
+) -> Vec<String> {
+    let max_distance = (word.chars().count() / 3).max(1);
+
+    let mut matches: Vec<(usize, String)> = candidates
+        .into_iter()
+        .map(|(suggestion, name)| (distance(word, name), suggestion))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    matches.sort();
+    matches.dedup_by(|a, b| a.1 == b.1);
+
+    matches
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, suggestion)| suggestion)
+        .collect()
+}
+
+/// Calculates the edit distance between two strings, counting insertions, deletions,
+/// substitutions and transpositions of adjacent characters as one edit each
+/// # Arguments
+/// * `a` - The first string
+/// * `b` - The second string
+pub fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // The distances for the previous two rows and the current row
+    let mut prev2: Vec<usize> = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur: Vec<usize> = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        cur[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                cur[j] = cur[j].min(prev2[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut prev2, &mut prev);
+        std::mem::swap(&mut prev, &mut cur);
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("echo", "echo"), 0);
+        assert_eq!(distance("ehco", "echo"), 1);
+        assert_eq!(distance("ech", "echo"), 1);
+        assert_eq!(distance("echoo", "echo"), 1);
+        assert_eq!(distance("acho", "echo"), 1);
+        assert_eq!(distance("", "echo"), 4);
+        assert_eq!(distance("größe", "grösse"), 2);
+    }
+
+    #[test]
+    fn test_suggest() {
+        let candidates = || {
+            [
+                ("echo".to_string(), "echo"),
+                ("exit".to_string(), "exit"),
+                ("tag".to_string(), "tag"),
+                ("tag add".to_string(), "add"),
+            ]
+        };
+
+        assert_eq!(suggest("ehco", candidates()), vec!["echo"]);
+        assert_eq!(suggest("ad", candidates()), vec!["tag add"]);
+        assert_eq!(suggest("tg", candidates()), vec!["tag"]);
+        assert!(suggest("something", candidates()).is_empty());
+    }
+}
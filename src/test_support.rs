@@ -0,0 +1,28 @@
+//! Helpers shared by the unit tests and the integration tests
+use std::{
+    future::Future,
+    ptr,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+/// The functions of a waker that does nothing, the futures are polled until they are ready anyway
+const NOOP_VTABLE: RawWakerVTable =
+    RawWakerVTable::new(|_| noop_raw_waker(), |_| {}, |_| {}, |_| {});
+
+/// Creates a raw waker that does nothing when it is woken
+fn noop_raw_waker() -> RawWaker {
+    RawWaker::new(ptr::null(), &NOOP_VTABLE)
+}
+
+/// Polls a future that never has to wait to completion
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    // SAFETY: The functions of the vtable ignore the data pointer, so they uphold the contract
+    let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
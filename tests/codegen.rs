@@ -0,0 +1,713 @@
+use std::io::Write;
+
+use clik::{
+    clik_command,
+    error::{self, PipeError},
+    ArgSpec, Context, OptSpec, Pipe, CLI,
+};
+
+#[cfg(feature = "async")]
+#[path = "../src/test_support.rs"]
+mod test_support;
+#[cfg(feature = "async")]
+use test_support::block_on;
+
+struct State {
+    number: i32,
+}
+
+#[clik_command(echo, "Prints out the supplied number")]
+#[clik_arg(number, "The number to echo back")]
+fn echo_command(state: &mut State, number: i32, text: String) {
+    state.number = number;
+    let _ = text;
+    Ok(())
+}
+
+#[clik_command(expect, "Fails if the number differs from the stored one")]
+fn expect_command(state: &mut State, number: i32) {
+    if state.number != number {
+        return Err(format!("Expected {number}, got {}", state.number).into());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_arg_specs() {
+    let command = echo_command();
+
+    assert_eq!(
+        command.args(),
+        [
+            ArgSpec {
+                name: "number",
+                ty: "i32",
+                position: 0,
+                help: Some("The number to echo back"),
+                required: true,
+                variadic: false,
+                default: None,
+            },
+            ArgSpec {
+                name: "text",
+                ty: "String",
+                position: 1,
+                help: None,
+                required: true,
+                variadic: false,
+                default: None,
+            }
+        ]
+    );
+    assert_eq!(command.usage(), "echo <number: i32> <text: String>");
+}
+
+#[test]
+fn test_command_help() {
+    let mut cli = CLI::new(State { number: 0 });
+    cli.add_command(echo_command());
+
+    let help = cli.help(&["echo"]).unwrap();
+    let lines: Vec<&str> = help.lines().collect();
+    assert_eq!(lines[0], "Usage: echo <number: i32> <text: String>");
+    assert_eq!(lines[2], "Prints out the supplied number");
+    assert_eq!(lines[4], "Arguments:");
+    assert!(
+        lines[5].starts_with("  number: i32 ") && lines[5].ends_with(" The number to echo back")
+    );
+    assert_eq!(lines[6].trim_end(), "  text: String");
+}
+
+#[test]
+fn test_parse_args() {
+    let mut cli = CLI::new(State { number: 0 });
+    cli.add_command(echo_command());
+
+    cli.handle("echo 5 five").unwrap();
+    assert!(cli.handle("echo 6").is_err());
+    assert!(cli.handle("echo six 6").is_err());
+}
+
+#[clik_command(length, "Sets the number to the length of the context")]
+fn length_command(state: &mut State, context: String) {
+    state.number = context.len() as i32;
+    Ok(())
+}
+
+#[test]
+fn test_context_arg() {
+    let mut cli = CLI::new(State { number: 0 });
+    cli.add_command(length_command());
+
+    cli.handle("length four").unwrap();
+    assert_eq!(cli.state().number, 4);
+}
+
+#[clik_command(first, "Sets the first number, ignoring the others", extra_args)]
+fn first_command(state: &mut State, number: i32) {
+    state.number = number;
+    Ok(())
+}
+
+#[test]
+fn test_too_many_args() {
+    let mut cli = CLI::new(State { number: 0 });
+    cli.add_command(echo_command());
+    cli.add_command(first_command());
+    cli.add_command(sum_command());
+
+    let err = cli
+        .handle("echo 5 five -v 6")
+        .expect_err("Expected too many arguments");
+    match err.downcast_ref::<error::Error>() {
+        Some(error::Error::TooManyArguments {
+            path,
+            position,
+            arguments,
+        }) => {
+            assert_eq!(path, &["echo"]);
+            assert_eq!(*position, 2);
+            assert_eq!(arguments, &["-v", "6"]);
+        }
+        other => panic!("Expected too many arguments, got {other:?}"),
+    }
+    assert_eq!(cli.state().number, 0);
+
+    // Subcommands are described with their full path
+    let mut set = set_command();
+    set.add_subcommand(echo_command());
+    cli.add_command(set);
+    let err = cli
+        .handle("set echo 5 five 6")
+        .expect_err("Expected too many arguments");
+    assert!(matches!(
+        err.downcast_ref::<error::Error>(),
+        Some(error::Error::TooManyArguments { path, .. }) if path == &["set", "echo"]
+    ));
+
+    cli.handle("first 1 2 3").unwrap();
+    assert_eq!(cli.state().number, 1);
+    cli.handle("sum 1 2 3").unwrap();
+    assert_eq!(cli.state().number, 6);
+}
+
+#[clik_command(set, "Sets the number, resets it if no number is supplied")]
+#[clik_arg(number, "The number to set")]
+fn set_command(state: &mut State, number: Option<i32>) {
+    state.number = number.unwrap_or_default();
+    Ok(())
+}
+
+#[test]
+fn test_optional_arg() {
+    let command = set_command();
+    assert_eq!(command.usage(), "set [number: i32]");
+    assert!(!command.args()[0].required);
+
+    let mut cli = CLI::new(State { number: 0 });
+    cli.add_command(command);
+
+    cli.add_command(expect_command());
+
+    cli.handle("set 5").unwrap();
+    cli.handle("expect 5").unwrap();
+    cli.handle("set").unwrap();
+    cli.handle("expect 0").unwrap();
+    assert!(cli.handle("set five").is_err());
+}
+
+#[clik_command(sum, "Sets the number to the sum of the supplied numbers")]
+#[clik_arg(numbers, "The numbers to sum up")]
+fn sum_command(state: &mut State, start: Option<i32>, numbers: Vec<i32>) {
+    state.number = start.unwrap_or_default() + numbers.iter().sum::<i32>();
+    Ok(())
+}
+
+#[clik_command(tag, "Adds at least one tag")]
+#[clik_arg(tags, "The tags to add", required)]
+fn tag_command(state: &mut State, id: i32, tags: Vec<String>) {
+    state.number = id + tags.len() as i32;
+    Ok(())
+}
+
+#[test]
+fn test_variadic_arg() {
+    let mut cli = CLI::new(State { number: 0 });
+    cli.add_command(sum_command());
+    cli.add_command(expect_command());
+
+    cli.handle("sum").unwrap();
+    cli.handle("expect 0").unwrap();
+    cli.handle("sum 1").unwrap();
+    cli.handle("expect 1").unwrap();
+    cli.handle("sum 1 2 3 4").unwrap();
+    cli.handle("expect 10").unwrap();
+
+    let err = cli.handle("sum 1 2 x 4").expect_err("Expected parse error");
+    match err.downcast_ref::<error::Error>() {
+        Some(error::Error::InvalidArgument {
+            path,
+            name,
+            position,
+            ..
+        }) => {
+            assert_eq!(path, &["sum"]);
+            assert_eq!((name.as_str(), *position), ("numbers", 2));
+        }
+        other => panic!("Expected invalid argument, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_required_variadic_arg() {
+    let command = tag_command();
+    assert_eq!(command.usage(), "tag <id: i32> <tags: String...>");
+    assert!(command.args()[1].variadic);
+
+    let mut cli = CLI::new(State { number: 0 });
+    cli.add_command(command);
+    cli.add_command(expect_command());
+
+    cli.handle("tag 1 a b").unwrap();
+    cli.handle("expect 3").unwrap();
+
+    let err = cli.handle("tag 1").expect_err("Expected missing tags");
+    match err.downcast_ref::<error::Error>() {
+        Some(error::Error::MissingArgument {
+            path,
+            name,
+            position,
+            ty,
+        }) => {
+            assert_eq!(path, &["tag"]);
+            assert_eq!((name.as_str(), *position), ("tags", 1));
+            assert_eq!(ty, "String");
+        }
+        other => panic!("Expected missing argument, got {other:?}"),
+    }
+}
+
+#[clik_command(count, "Counts up from the supplied number")]
+#[clik_opt(step, short = 'n', long = "count", "The step size")]
+#[clik_opt(double, short = 'd')]
+#[clik_opt(dry_run, "Do not store the result")]
+fn count_command(state: &mut State, start: i32, step: Option<i32>, double: bool, dry_run: bool) {
+    let mut number = start + step.unwrap_or(1);
+    if double {
+        number *= 2;
+    }
+    if !dry_run {
+        state.number = number;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_opt_specs() {
+    let command = count_command();
+    assert_eq!(
+        command.usage(),
+        "count [-n|--count <i32>] [-d] [--dry-run] <start: i32>"
+    );
+    assert_eq!(command.args().len(), 1);
+    assert_eq!(
+        command.opts()[0],
+        OptSpec {
+            name: "step",
+            ty: "i32",
+            short: Some('n'),
+            long: Some("count"),
+            help: Some("The step size"),
+            flag: false,
+        }
+    );
+    assert!(command.opts()[2].flag);
+    assert_eq!(command.opts()[2].long, Some("dry-run"));
+}
+
+#[test]
+fn test_opts() {
+    let mut cli = CLI::new(State { number: 0 });
+    cli.add_command(count_command());
+    cli.add_command(expect_command());
+
+    cli.handle("count 1").unwrap();
+    cli.handle("expect 2").unwrap();
+    cli.handle("count -n 3 -d 1").unwrap();
+    cli.handle("expect 8").unwrap();
+    cli.handle("count --count=-5 -- -1").unwrap();
+    cli.handle("expect -6").unwrap();
+    cli.handle("count --dry-run 10").unwrap();
+    cli.handle("expect -6").unwrap();
+    cli.handle("count -dn3 1").unwrap();
+    cli.handle("expect 8").unwrap();
+
+    let err = cli
+        .handle("count -x 1")
+        .expect_err("Expected unknown option");
+    assert!(matches!(
+        err.downcast_ref::<error::Error>(),
+        Some(error::Error::UnknownOption { path, option, .. }) if path == &["count"] && option == "-x"
+    ));
+    let err = cli
+        .handle("count -d 1 -d")
+        .expect_err("Expected duplicate option");
+    assert!(matches!(
+        err.downcast_ref::<error::Error>(),
+        Some(error::Error::DuplicateOption { name, .. }) if name == "double"
+    ));
+    let err = cli
+        .handle("count -n x 1")
+        .expect_err("Expected parse error");
+    assert!(matches!(
+        err.downcast_ref::<error::Error>(),
+        Some(error::Error::InvalidArgument { name, position: 1, .. }) if name == "step"
+    ));
+
+    // Positions count all supplied arguments, including the options
+    let err = cli
+        .handle("count -n 3 x")
+        .expect_err("Expected parse error");
+    assert!(matches!(
+        err.downcast_ref::<error::Error>(),
+        Some(error::Error::InvalidArgument { name, position: 2, .. }) if name == "start"
+    ));
+    let err = cli
+        .handle("count -n 3 1 -d 5 6")
+        .expect_err("Expected too many arguments");
+    assert!(matches!(
+        err.downcast_ref::<error::Error>(),
+        Some(error::Error::TooManyArguments { position: 4, arguments, .. }) if arguments == &["5", "6"]
+    ));
+    let err = cli
+        .handle("count -d -- 1 2")
+        .expect_err("Expected too many arguments");
+    assert!(matches!(
+        err.downcast_ref::<error::Error>(),
+        Some(error::Error::TooManyArguments { position: 3, .. })
+    ));
+}
+
+#[clik_command(add, "Adds a number")]
+#[clik_arg(amount, "How much to add", default = 10)]
+#[clik_arg(times, "How often to add", default = 2 - 1)]
+fn add_command(state: &mut State, amount: i32, times: i32) {
+    state.number += amount * times;
+    Ok(())
+}
+
+#[test]
+fn test_default_arg() {
+    let command = add_command();
+    assert_eq!(command.usage(), "add [amount: i32 = 10] [times: i32 = 2-1]");
+    assert_eq!(command.args()[0].default, Some("10"));
+    assert!(!command.args()[0].required);
+
+    let mut cli = CLI::new(State { number: 0 });
+    cli.add_command(command);
+    cli.add_command(expect_command());
+
+    let help = cli.help(&["add"]).unwrap();
+    assert!(help.contains(" How much to add (default: 10)\n"));
+
+    cli.handle("add").unwrap();
+    cli.handle("expect 10").unwrap();
+    cli.handle("add 5").unwrap();
+    cli.handle("expect 15").unwrap();
+    cli.handle("add 5 3").unwrap();
+    cli.handle("expect 30").unwrap();
+    assert!(cli.handle("add five").is_err());
+}
+
+#[clik_command(range, "Lists the numbers up to the stored number")]
+fn range_command(
+    state: &mut State,
+    start: Option<i32>,
+) -> Result<Vec<i32>, Box<dyn std::error::Error>> {
+    Ok((start.unwrap_or(0)..state.number).collect())
+}
+
+#[clik_command(multiple, "Keeps the piped numbers that are a multiple of the factor")]
+fn multiple_command(
+    _state: &mut State,
+    input: Pipe<Vec<i32>>,
+    factor: i32,
+) -> Result<Vec<i32>, Box<dyn std::error::Error>> {
+    Ok(input.into_iter().filter(|n| n % factor == 0).collect())
+}
+
+#[clik_command(store, "Stores the number of piped numbers")]
+fn store_command(state: &mut State, input: Pipe<Vec<i32>>) {
+    state.number = input.into_iter().count() as i32;
+    Ok(())
+}
+
+#[test]
+fn test_pipeline() {
+    let mut cli = CLI::new(State { number: 10 });
+    cli.add_command(range_command());
+    cli.add_command(multiple_command());
+    cli.add_command(store_command());
+    cli.add_command(set_command());
+
+    assert_eq!(
+        multiple_command().usage(),
+        "multiple <factor: i32>",
+        "The piped input is not an argument"
+    );
+
+    cli.handle("range 1 | multiple 3 | store").unwrap();
+    assert_eq!(cli.state().number, 3);
+    cli.handle("set 10; range | store").unwrap();
+    assert_eq!(cli.state().number, 10);
+    cli.handle("range").unwrap();
+
+    let err = cli
+        .handle("range | set 4")
+        .expect_err("Expected set to reject the piped input");
+    assert!(matches!(
+        err.downcast_ref::<error::Error>(),
+        Some(error::Error::Pipe {
+            path,
+            inner: PipeError::InputNotAccepted
+        }) if path == &["set"]
+    ));
+    let err = cli
+        .handle("range | range")
+        .expect_err("Expected range to reject the piped input");
+    assert!(matches!(
+        err.downcast_ref::<error::Error>(),
+        Some(error::Error::Pipe { path, .. }) if path == &["range"]
+    ));
+    assert!(cli.handle("range | multiple x").is_err());
+}
+
+#[clik_command(show, "Writes the invocation to the output")]
+fn show_command(ctx: &mut Context<State>, number: i32) {
+    ctx.state_mut().number = number;
+    let path = ctx.path().join(" ");
+    ctx.set_variable("last", path);
+    let line = format!("{} {}", ctx.line(), ctx.args()[0].name);
+    writeln!(ctx, "{line}")?;
+    Ok(())
+}
+
+#[clik_command(doubled, "Doubles the piped numbers")]
+fn doubled_command(
+    ctx: &mut Context<State>,
+    input: Pipe<Vec<i32>>,
+) -> Result<Vec<i32>, Box<dyn std::error::Error>> {
+    ctx.state_mut().number += 1;
+    Ok(input.into_iter().map(|n| n * 2).collect())
+}
+
+#[test]
+fn test_context() {
+    let mut cli = CLI::new(State { number: 0 });
+    cli.add_command(show_command());
+    cli.add_command(doubled_command());
+    cli.add_command(range_command());
+    cli.add_command(store_command());
+
+    let output = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    cli.set_output(SharedOutput(output.clone()));
+
+    cli.handle("show 3").unwrap();
+    assert_eq!(cli.state().number, 3);
+    assert_eq!(cli.variable("last"), Some("show"));
+    assert_eq!(output.lock().unwrap().as_slice(), b"show 3 number\n");
+    assert!(cli.handle("show x").is_err());
+
+    cli.handle("range | doubled | store").unwrap();
+    assert_eq!(cli.state().number, 3);
+}
+
+/// An output that can still be read after it has been handed to the CLI
+struct SharedOutput(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// An error type that tells the errors of clik apart from the errors of the commands
+#[derive(Debug)]
+enum AppError {
+    Usage(String),
+    Denied(i32),
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Usage(e) => write!(f, "{e}"),
+            Self::Denied(number) => write!(f, "{number} is denied"),
+        }
+    }
+}
+
+impl<E: std::error::Error> From<E> for AppError {
+    fn from(e: E) -> Self {
+        Self::Usage(e.to_string())
+    }
+}
+
+#[clik_command(allow, "Fails for negative numbers")]
+#[clik_opt(force, short = 'f')]
+fn allow_command(state: &mut State, number: i32, force: bool) -> Result<(), AppError> {
+    if number < 0 && !force {
+        return Err(AppError::Denied(number));
+    }
+    state.number = number;
+    Ok(())
+}
+
+#[clik_command(negate, "Negates the piped numbers")]
+fn negate_command(_state: &mut State, input: Pipe<Vec<i32>>) -> Result<Vec<i32>, AppError> {
+    Ok(input.into_iter().map(|n| -n).collect())
+}
+
+#[test]
+fn test_error_type() {
+    let mut cli: CLI<State, AppError> = CLI::with_error_type(State { number: 0 });
+    cli.add_command(allow_command());
+    cli.add_command(negate_command());
+
+    cli.handle("allow 5").unwrap();
+    cli.handle("allow -f -- -5").unwrap();
+    assert_eq!(cli.state().number, -5);
+
+    assert!(matches!(cli.handle("allow -1"), Err(AppError::Denied(-1))));
+    assert!(matches!(cli.handle("allow"), Err(AppError::Usage(_))));
+    assert!(matches!(cli.handle("allow -x 1"), Err(AppError::Usage(_))));
+    assert!(matches!(cli.handle("nothing"), Err(AppError::Usage(_))));
+    assert!(matches!(
+        cli.handle("negate | allow 1"),
+        Err(AppError::Usage(_))
+    ));
+}
+
+#[cfg(feature = "async")]
+#[clik_command(wait, "Sets the number after yielding")]
+async fn wait_command(state: &mut State, number: i32) {
+    std::future::ready(()).await;
+    state.number = number;
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[clik_command(announce, "Writes the number to the output after yielding")]
+async fn announce_command(ctx: &mut Context<'_, State>, prefix: String) {
+    std::future::ready(()).await;
+    let number = ctx.state().number;
+    writeln!(ctx, "{prefix} {number}")?;
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async() {
+    let mut cli = CLI::new(State { number: 0 });
+    cli.add_command(wait_command());
+    cli.add_command(announce_command());
+
+    let output = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    cli.set_output(SharedOutput(output.clone()));
+
+    block_on(cli.handle_async("wait 4")).unwrap();
+    block_on(cli.handle_async("announce number:")).unwrap();
+    assert_eq!(output.lock().unwrap().as_slice(), b"number: 4\n");
+
+    let err = block_on(cli.handle_async("wait")).expect_err("Expected missing argument");
+    assert!(matches!(
+        err.downcast_ref::<error::Error>(),
+        Some(error::Error::MissingArgument { path, .. }) if path == &["wait"]
+    ));
+
+    // Async commands take part in pipelines and redirections of the async handler
+    cli.add_command(range_command());
+    cli.add_command(store_command());
+    block_on(cli.handle_async("wait 3 | announce piped:")).unwrap();
+    block_on(cli.handle_async("range | store; announce stored:")).unwrap();
+    assert_eq!(
+        output.lock().unwrap().as_slice(),
+        b"number: 4\npiped: 3\nstored: 3\n"
+    );
+    let err = block_on(cli.handle_async("range | wait 2")).expect_err("Expected pipe error");
+    assert!(matches!(
+        err.downcast_ref::<error::Error>(),
+        Some(error::Error::Pipe { path, inner: PipeError::InputNotAccepted }) if path == &["wait"]
+    ));
+
+    let err = cli
+        .handle("wait 5")
+        .expect_err("Expected sync handler error");
+    assert!(matches!(
+        err.downcast_ref::<error::Error>(),
+        Some(error::Error::AsyncCommand { path }) if path == &["wait"]
+    ));
+}
+
+#[cfg(feature = "async")]
+#[clik_command(scale, "Multiplies the number by the factor")]
+#[clik_opt(negate, short = 'n')]
+fn scale_command(state: &mut State, factor: i32, negate: bool) {
+    state.number *= if negate { -factor } else { factor };
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[clik_command(scale, "Multiplies the number by the factor")]
+#[clik_opt(negate, short = 'n')]
+async fn scale_async_command(state: &mut State, factor: i32, negate: bool) {
+    std::future::ready(()).await;
+    state.number *= if negate { -factor } else { factor };
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[clik_command(math, "Calculate things")]
+fn math_command(state: &mut State) {
+    let _ = state;
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_consistency() {
+    /// Creates a CLI with `scale` nested below a parent that takes no arguments
+    fn create_cli(scale: clik::Command<'static, State>) -> CLI<'static, State> {
+        let mut cli = CLI::new(State { number: 1 });
+        let mut math = math_command();
+        math.add_subcommand(scale);
+        cli.add_command(math);
+        cli.add_help_command();
+        cli
+    }
+
+    /// Handles a line through the synchronous or the async handler, resetting the number
+    fn run(cli: &mut CLI<State>, line: &str, handle_async: bool) -> Result<i32, String> {
+        let result = match handle_async {
+            false => cli.handle(line),
+            true => block_on(cli.handle_async(line)),
+        };
+        let result = result.map(|_| cli.state().number);
+        cli.state_mut().number = 1;
+        result.map_err(|e| format!("{e:?}"))
+    }
+
+    /// Handles a line through both handlers of the sync command and the async handler
+    /// of the async command, the results have to be identical
+    fn handle(
+        sync: &mut CLI<State>,
+        asynchronous: &mut CLI<State>,
+        line: &str,
+    ) -> Result<i32, String> {
+        let result = run(sync, line, false);
+        assert_eq!(
+            result,
+            run(sync, line, true),
+            "Expected identical results for '{line}'"
+        );
+        assert_eq!(
+            result,
+            run(asynchronous, line, true),
+            "Expected identical results for '{line}'"
+        );
+        result
+    }
+
+    let mut sync = create_cli(scale_command());
+    let mut asynchronous = create_cli(scale_async_command());
+
+    let mut error =
+        |line| handle(&mut sync, &mut asynchronous, line).expect_err("Expected an error");
+    assert!(error("mth scale 2").starts_with("UnknownCommand"));
+    assert!(error("math sclae 2").starts_with("UnknownSubcommand"));
+    assert!(error("help math sclae").starts_with("UnknownSubcommand"));
+    assert!(error("math scale").starts_with("MissingArgument"));
+    assert!(error("math scale x").starts_with("InvalidArgument"));
+    assert!(error("math scale 2 3").starts_with("TooManyArguments"));
+    assert!(error("math scale -x 2").starts_with("UnknownOption"));
+    assert!(error("math scale -n -n 2").starts_with("DuplicateOption"));
+    assert!(error("math scale \"2").starts_with("Lex"));
+
+    assert_eq!(handle(&mut sync, &mut asynchronous, "math scale 3"), Ok(3));
+    assert_eq!(
+        handle(&mut sync, &mut asynchronous, "math scale -n 3"),
+        Ok(-3)
+    );
+    assert_eq!(
+        handle(&mut sync, &mut asynchronous, "mth || math scale 2"),
+        Ok(2)
+    );
+    assert_eq!(
+        handle(&mut sync, &mut asynchronous, "math scale 2 | math scale 3"),
+        Ok(6)
+    );
+}
@@ -0,0 +1,11 @@
+use clik::clik_command;
+
+struct State;
+
+#[clik_command(read, "Reads a file")]
+fn read_command(state: &mut State, path: String) -> std::io::Result<()> {
+    std::fs::read(path)?;
+    Ok(())
+}
+
+fn main() {}